@@ -1,52 +1,242 @@
-use proc_macro::TokenStream;
 use proc_macro2::{TokenStream as TokenStream2};
 use quote::quote;
+use syn::spanned::Spanned;
 
-fn crate_or_name(name: String) -> syn::Ident {
-    let crate_name = std::env::var("CARGO_PKG_NAME").unwrap();
-    if crate_name == name {
-        syn::Ident::new(&"crate", proc_macro2::Span::call_site())
+// Path of the microservice crate in the generated code: the path given with `crate = path`, else the name
+// under which the crate being compiled depends on microservice (renamed dependencies included)
+fn crate_path(path: Option<&syn::Path>) -> syn::Result<syn::Path> {
+    if let Some(path) = path {
+        return Ok(path.clone());
     }
-    else {
-        syn::Ident::new(&name, proc_macro2::Span::call_site())
+    match proc_macro_crate::crate_name("microservice") {
+        Ok(proc_macro_crate::FoundCrate::Itself) => Ok(syn::parse_quote! { crate }),
+        Ok(proc_macro_crate::FoundCrate::Name(name)) => {
+            let name = syn::Ident::new(&name, proc_macro2::Span::call_site());
+            Ok(syn::parse_quote! { ::#name })
+        },
+        // Without a direct dependency (re-export by a facade crate) the path is expected with `crate = path`
+        Err(error) => Err(syn::Error::new(proc_macro2::Span::call_site(), format!(
+            "unable to find the microservice crate in the dependencies ({}), give its path with the option `crate = path`, e.g. `crate = facade::microservice`",
+            error
+        )))
+    }
+}
+
+// Parse the `crate = path` option of the macros
+fn parse_crate_option(input: syn::parse::ParseStream) -> syn::Result<syn::Path> {
+    input.parse::<syn::Token![crate]>()?;
+    input.parse::<syn::Token![=]>()?;
+    input.parse()
+}
+
+// Arguments of #[injector] and of the #[injection] attribute of #[derive(Inject)]: an optional `crate = path`
+pub struct CrateArgs {
+    pub crate_path: Option<syn::Path>
+}
+
+impl syn::parse::Parse for CrateArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(CrateArgs { crate_path: None });
+        }
+        let crate_path = parse_crate_option(input)?;
+        input.parse::<Option<syn::Token![,]>>()?;
+        if !input.is_empty() {
+            return Err(input.error("unexpected argument, expected `crate = path`"));
+        }
+        Ok(CrateArgs { crate_path: Some(crate_path) })
     }
 }
 
-pub fn impl_injectable(attributes: &Vec<syn::Ident>, ast: &mut syn::Item) -> TokenStream {
+// Give the span of a trait listed in the attribute to the generated tokens, the errors are reported at this trait
+fn respan(tokens: TokenStream2, span: proc_macro2::Span) -> TokenStream2 {
+    tokens.into_iter().map(|token| match token {
+        proc_macro2::TokenTree::Group(group) => {
+            let mut respanned = proc_macro2::Group::new(group.delimiter(), respan(group.stream(), span));
+            respanned.set_span(span);
+            proc_macro2::TokenTree::Group(respanned)
+        },
+        mut token => {
+            token.set_span(span);
+            token
+        }
+    }).collect()
+}
+
+// Register the component for each trait listed in the attribute
+fn register_traits(attributes: &[syn::Path], register_trait: &syn::Ident) -> Vec<TokenStream2> {
+    attributes.iter().map(|attribute| quote! {
+        <Self as #attribute>::#register_trait(entry.clone(), registry)?;
+    }).collect()
+}
+
+// Check if a type id is one of the traits listed in the attribute
+fn is_traits(attributes: &[syn::Path], id: TokenStream2) -> Vec<TokenStream2> {
+    attributes.iter().map(|attribute| quote! { <Self as #attribute>::is_trait(#id) }).collect()
+}
+
+// Storage strategy of a component selected with the option `storage = mutex | rwlock | shared`
+#[derive(Clone, Copy, PartialEq)]
+pub enum Storage {
+    Mutex,
+    RwLock,
+    Shared
+}
+
+impl Storage {
+    const ALL: [Storage; 3] = [Storage::Mutex, Storage::RwLock, Storage::Shared];
+
+    // Type of the handle sharing the component `ty`
+    fn handle(&self, ty: TokenStream2) -> TokenStream2 {
+        match self {
+            Storage::Mutex => quote! { std::sync::Arc<std::sync::Mutex<#ty>> },
+            Storage::RwLock => quote! { std::sync::Arc<std::sync::RwLock<#ty>> },
+            Storage::Shared => quote! { std::sync::Arc<#ty> }
+        }
+    }
+
+    // Name of the function generated in each trait to register the handles of this storage
+    fn register_trait(&self) -> syn::Ident {
+        let name = match self {
+            Storage::Mutex => "register_trait",
+            Storage::RwLock => "register_trait_rw",
+            Storage::Shared => "register_trait_shared"
+        };
+        syn::Ident::new(name, proc_macro2::Span::call_site())
+    }
+}
+
+// Arguments of #[injectable]: the traits of the component (generic traits included) and `key = value` options
+pub struct InjectableArgs {
+    pub traits: Vec<syn::Path>,
+    pub storage: Option<Storage>,
+    pub primary: bool,
+    pub priority: Option<i32>,
+    pub when: Option<syn::LitStr>,
+    pub crate_path: Option<syn::Path>,
+    // First `key = value` or `primary` option, reported when the options are used on a trait
+    pub option: Option<syn::Ident>
+}
+
+impl syn::parse::Parse for InjectableArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut args = InjectableArgs { traits: Vec::new(), storage: None, primary: false, priority: None, when: None, crate_path: None, option: None };
+        while !input.is_empty() {
+            if input.peek(syn::Token![crate]) && input.peek2(syn::Token![=]) {
+                args.crate_path = Some(parse_crate_option(input)?);
+                if !input.is_empty() {
+                    input.parse::<syn::Token![,]>()?;
+                }
+                continue;
+            }
+            let path: syn::Path = input.parse()?;
+            let ident = match path.get_ident() {
+                Some(ident) if input.peek(syn::Token![=]) || ident == "primary" => ident.clone(),
+                _ => {
+                    args.traits.push(path);
+                    if !input.is_empty() {
+                        input.parse::<syn::Token![,]>()?;
+                    }
+                    continue;
+                }
+            };
+            args.option.get_or_insert_with(|| ident.clone());
+            if input.peek(syn::Token![=]) {
+                input.parse::<syn::Token![=]>()?;
+                match ident.to_string().as_str() {
+                    "storage" => {
+                        let value: syn::Ident = input.parse()?;
+                        args.storage = Some(match value.to_string().as_str() {
+                            "mutex" => Storage::Mutex,
+                            "rwlock" => Storage::RwLock,
+                            "shared" => Storage::Shared,
+                            _ => return Err(syn::Error::new(value.span(), "unknown storage, expected mutex, rwlock or shared"))
+                        });
+                    },
+                    "priority" => {
+                        let negative = input.parse::<Option<syn::Token![-]>>()?.is_some();
+                        let priority: i32 = input.parse::<syn::LitInt>()?.base10_parse()?;
+                        args.priority = Some(if negative { -priority } else { priority });
+                    },
+                    "when" => {
+                        let condition: syn::LitStr = input.parse()?;
+                        if !is_condition(&condition.value()) {
+                            return Err(syn::Error::new(condition.span(), "invalid condition, expected \"field\", \"field == 'value'\" or \"field != 'value'\""));
+                        }
+                        args.when = Some(condition);
+                    },
+                    _ => return Err(syn::Error::new(ident.span(), "unknown #[injectable] option"))
+                }
+            }
+            else {
+                args.primary = true;
+            }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+// Check the syntax of a `when` condition, evaluated at runtime by microservice::config::Condition
+fn is_condition(condition: &str) -> bool {
+    let field = condition.split("!=").next().unwrap_or_default().split("==").next().unwrap_or_default().trim();
+    !field.is_empty() && !field.contains(char::is_whitespace)
+}
+
+pub fn impl_injectable(args: &InjectableArgs, ast: &mut syn::Item) -> syn::Result<TokenStream2> {
+    let attributes = &args.traits;
+    let name_crate = crate_path(args.crate_path.as_ref())?;
     match ast {
 	syn::Item::Trait(ref mut trait_data) => {
             let trait_name = trait_data.ident.clone();
-            let name_crate = crate_or_name("microservice".to_string());
-            // Recursive register function (a call is done for each constrained trait in the attribute list)
-            trait_data.items.push(syn::TraitItem::Verbatim(quote! {
+            let trait_generics = trait_data.generics.clone();
+            let (impl_generics, ty_generics, where_clause) = trait_generics.split_for_impl();
+            // The parameters of a generic trait are part of the TypeId of its handles
+            let static_parameters: Vec<&syn::Ident> = trait_generics.type_params().map(|param| &param.ident).collect();
+            if let Some(option) = &args.option {
+                return Err(syn::Error::new(option.span(), "the storage, primary, priority and when options can only be used on a struct or an enum"));
+            }
+            let is_traits = is_traits(attributes, quote! { id });
+            // Recursive register functions, one for each storage (a call is done for each constrained trait in the attribute list)
+            for storage in Storage::ALL.iter() {
+                let register_trait = storage.register_trait();
+                let register_traits = register_traits(attributes, &register_trait);
+                let handle = storage.handle(quote! { _COMPONENT_ });
+                let trait_handle = storage.handle(quote! { dyn #trait_name #ty_generics });
+                trait_data.items.push(syn::TraitItem::Verbatim(quote! {
 
-                // Register the structure whith the current trait in the registry
-                fn register_trait<T>(component_ref: std::sync::Arc<std::sync::Mutex<T>>, registry: &mut #name_crate::injection::Registry) where T: #trait_name +  #(#attributes +)* 'static, Self: Sized {
-                    #(<Self as #attributes>::register_trait(component_ref.clone(), registry);)*
-                    // #name_crate::trace!("Register trait {}", std::stringify!(#trait_name));
-                    registry.register_with_type::<dyn #trait_name>(component_ref);
-                }
+                    // Register the structure whith the current trait in the registry
+                    fn #register_trait<_COMPONENT_>(entry: #name_crate::injection::RegistryEntry<#handle>, registry: &mut #name_crate::injection::Registry) -> #name_crate::error::Result<()> where _COMPONENT_: #trait_name #ty_generics + #(#attributes +)* 'static, Self: Sized, #(#static_parameters: 'static),* {
+                        #(#register_traits)*
+                        // #name_crate::trace!("Register trait {}", std::stringify!(#trait_name));
+                        registry.register_with_type::<#trait_handle>(entry.map(|component| -> #trait_handle { component }))
+                    }
+                }));
+            }
+            trait_data.items.push(syn::TraitItem::Verbatim(quote! {
 
                 // Return if the structure implement a trait
-                fn is_trait(id: std::any::TypeId) -> bool where Self: Sized + 'static {
-                    std::any::TypeId::of::<dyn #trait_name>() == id #(|| <Self as #attributes>::is_trait(id))*
+                fn is_trait(id: std::any::TypeId) -> bool where Self: Sized + 'static, #(#static_parameters: 'static),* {
+                    std::any::TypeId::of::<dyn #trait_name #ty_generics>() == id #(|| #is_traits)*
                 }
             }));
 	    let output = quote! {
 		#ast
-                impl std::fmt::Debug for dyn #trait_name {
+                impl #impl_generics std::fmt::Debug for dyn #trait_name #ty_generics #where_clause {
                     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                         f.debug_struct(std::stringify!(#trait_name)).finish()
                     }
                 }
 	    };
-            proc_macro::TokenStream::from(output)
+            Ok(output)
 	},
-	syn::Item::Struct(ref mut struct_data) => {
-            let struct_name = struct_data.ident.clone();
+	syn::Item::Struct(syn::ItemStruct { ident, generics, .. }) | syn::Item::Enum(syn::ItemEnum { ident, generics, .. }) => {
+            let struct_name = ident.clone();
 
             // Extract optional generic parts
-            let generics = struct_data.generics.clone();
+            let generics = generics.clone();
             let sgenerics: Vec<TokenStream2> = generics.params.clone().into_iter().map(|g| {
                 match g {
                     syn::GenericParam::Type(t) => { let t = t.ident;  quote!{ #t } },
@@ -55,75 +245,341 @@ pub fn impl_injectable(attributes: &Vec<syn::Ident>, ast: &mut syn::Item) -> Tok
                 }
             }).collect();
             let where_clause = generics.where_clause.clone();
-            let name_crate = crate_or_name("microservice".to_string());
+
+            // Handle sharing the structure in the registry
+            let storage = args.storage.unwrap_or(Storage::Mutex);
+            let handle = storage.handle(quote! { Self });
+            let into_handle = match storage {
+                Storage::Mutex => quote! { std::sync::Arc::new(std::sync::Mutex::new(self)) },
+                Storage::RwLock => quote! { std::sync::Arc::new(std::sync::RwLock::new(self)) },
+                Storage::Shared => quote! { std::sync::Arc::new(self) }
+            };
+            let register_trait = storage.register_trait();
+            let trait_handle = storage.handle(quote! { T });
+            // Uses of the traits listed in the attribute through a check, one for each trait: a trait not implemented
+            // by the structure is reported once at its name
+            let indexes: Vec<usize> = (0..attributes.len()).collect();
+            let implemented = attributes.iter().zip(&indexes).map(|(attribute, index)| respan(quote! {
+                impl ListedTrait<#index> {
+                    fn listed_trait_must_be_implemented<T>() -> ListedTraitFunctions<#trait_handle> where T: #attribute + 'static {
+                        ListedTraitFunctions { register: <T as #attribute>::#register_trait, is_trait: <T as #attribute>::is_trait }
+                    }
+                }
+            }, attribute.span()));
+            let register_traits = attributes.iter().zip(&indexes).map(|(attribute, index)| respan(quote! {
+                (ListedTrait::<#index>::listed_trait_must_be_implemented::<Self>().register)(entry.clone(), registry)?;
+            }, attribute.span()));
+            let is_traits = attributes.iter().zip(&indexes).map(|(attribute, index)| respan(quote! {
+                (ListedTrait::<#index>::listed_trait_must_be_implemented::<Self>().is_trait)(std::any::TypeId::of::<_TRAIT_>())
+            }, attribute.span()));
+            let primary = args.primary;
+            let priority = args.priority.unwrap_or(0);
+            let condition = match &args.when {
+                Some(condition) => quote! { Some(#condition) },
+                None => quote! { None }
+            };
 
             // Compose the result
 	    let output = quote! {
 		#ast
-                impl #generics #name_crate::injection::Component for #struct_name<#(#sgenerics),*> #where_clause {
-                    // Function to register the structure and all its traits in the registry
-                    fn register(component_ref: std::sync::Arc<std::sync::Mutex<Self>>, registry: &mut #name_crate::injection::Registry) where Self: Sized + 'static {
-                        registry.register_with_type::<#struct_name<#(#sgenerics),*>>(component_ref.clone());
-                        #(<Self as #attributes>::register_trait(component_ref.clone(), registry);)*
-                        // #name_crate::trace!("Register struct {}", std::stringify!(#struct_name));
+                const _: () = {
+                    #[allow(dead_code)]
+                    struct ListedTrait<const N: usize>;
+                    #[allow(dead_code)]
+                    struct ListedTraitFunctions<H> {
+                        register: fn(#name_crate::injection::RegistryEntry<H>, &mut #name_crate::injection::Registry) -> #name_crate::error::Result<()>,
+                        is_trait: fn(std::any::TypeId) -> bool
                     }
+                    #(#implemented)*
+
+                    impl #generics #name_crate::injection::ComponentStorage for #struct_name<#(#sgenerics),*> #where_clause {
+                        type Handle = #handle;
+                        const PRIMARY: bool = #primary;
+                        const PRIORITY: i32 = #priority;
+                        const CONDITION: Option<&'static str> = #condition;
 
-                    // Function to return if a trait is implemented in the structure
-                    fn struct_impl_trait<_TRAIT_>() -> bool where _TRAIT_: ?Sized + 'static, Self: Sized + 'static {
-                        false #(|| <Self as #attributes>::is_trait(std::any::TypeId::of::<_TRAIT_>()))*
+                        fn into_handle(self) -> Self::Handle {
+                            #into_handle
+                        }
+
+                        // Function to register the structure and all its traits in the registry
+                        fn register(entry: #name_crate::injection::RegistryEntry<Self::Handle>, registry: &mut #name_crate::injection::Registry) -> #name_crate::error::Result<()> where Self: Sized + 'static {
+                            registry.register_with_type::<Self::Handle>(entry.clone())?;
+                            #(#register_traits)*
+                            // #name_crate::trace!("Register struct {}", std::stringify!(#struct_name));
+                            Ok(())
+                        }
                     }
-                }
+
+                    impl #generics #name_crate::injection::Component for #struct_name<#(#sgenerics),*> #where_clause {
+                        // Function to return if a trait is implemented in the structure
+                        fn struct_impl_trait<_TRAIT_>() -> bool where _TRAIT_: ?Sized + 'static, Self: Sized + 'static {
+                            false #(|| #is_traits)*
+                        }
+                    }
+                };
 	    };
-            proc_macro::TokenStream::from(output)
+            Ok(output)
 	},
-	_ => {
-	    panic!("This is not a struct nor a trait")
-	}
+	_ => Err(syn::Error::new_spanned(ast, "#[injectable] can only be used on a struct, an enum or a trait"))
     }
 }
 
-fn inject_method(method_data: &syn::ImplItemMethod) -> syn::ImplItem {
-    let name_crate = crate_or_name("microservice".to_string());
+// Remove the attribute `name` from a parameter or a field, it can be used only once
+fn take_attribute(attrs: &mut Vec<syn::Attribute>, name: &str) -> syn::Result<Option<syn::Attribute>> {
+    let mut found: Option<syn::Attribute> = None;
+    for attr in std::mem::take(attrs) {
+        if !attr.path.is_ident(name) {
+            attrs.push(attr);
+        }
+        else if found.is_some() {
+            return Err(syn::Error::new_spanned(attr, format!("#[{}] is used more than once", name)));
+        }
+        else {
+            found = Some(attr);
+        }
+    }
+    Ok(found)
+}
+
+// Extract the #[inject] attribute of a method with its optional profile: #[inject(profile = "test")]
+fn take_inject(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<Option<syn::LitStr>>> {
+    take_attribute(attrs, "inject")?.map(|attr| {
+        if attr.tokens.is_empty() {
+            return Ok(None);
+        }
+        attr.parse_args_with(|input: syn::parse::ParseStream| {
+            let key: syn::Ident = input.parse()?;
+            if key != "profile" {
+                return Err(syn::Error::new(key.span(), "unknown #[inject] option, expected #[inject(profile = \"profile\")]"));
+            }
+            input.parse::<syn::Token![=]>()?;
+            input.parse::<syn::LitStr>()
+        }).map(Some)
+    }).transpose()
+}
+
+// Extract the qualifier of a parameter declared with #[named("qualifier")] and remove the attribute
+fn take_qualifier(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<syn::LitStr>> {
+    take_attribute(attrs, "named")?.map(|attr| {
+        attr.parse_args::<syn::LitStr>()
+            .map_err(|_| syn::Error::new_spanned(attr, "#[named] expects a string literal: #[named(\"qualifier\")]"))
+    }).transpose()
+}
+
+// Extract the config field of a parameter declared with #[config("field")] or #[config(section = "field")]
+// and remove the attribute
+fn take_config_field(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<syn::LitStr>> {
+    take_attribute(attrs, "config")?.map(|attr| {
+        attr.parse_args_with(|input: syn::parse::ParseStream| {
+            if !input.peek(syn::LitStr) {
+                let key: syn::Ident = input.parse()?;
+                if key != "section" {
+                    return Err(syn::Error::new(key.span(), "unknown #[config] option, expected #[config(\"field\")] or #[config(section = \"field\")]"));
+                }
+                input.parse::<syn::Token![=]>()?;
+            }
+            input.parse::<syn::LitStr>()
+        })
+    }).transpose()
+}
+
+// Extract the default value of a parameter declared with #[default] (Default::default()) or #[default(expression)]
+// and remove the attribute
+fn take_default(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<(syn::Attribute, TokenStream2)>> {
+    take_attribute(attrs, "default")?.map(|attr| {
+        if attr.tokens.is_empty() {
+            return Ok((attr, quote! { Default::default() }));
+        }
+        match attr.parse_args::<syn::Expr>() {
+            Ok(expression) => Ok((attr, quote! { #expression })),
+            Err(_) => Err(syn::Error::new_spanned(attr, "#[default] expects an expression: #[default(expression)]"))
+        }
+    }).transpose()
+}
+
+// Return if the type is a path whose last segment is `name` (Vec<T>, std::vec::Vec<T>...)
+fn is_type(ty: &syn::Type, name: &str) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path.path.segments.last().map_or(false, |segment| segment.ident == name),
+        _ => false
+    }
+}
+
+// Return the first generic argument of a type path (T for Vec<T>)
+fn generic_argument(ty: &syn::Type) -> Option<&syn::Type> {
+    match ty {
+        syn::Type::Path(type_path) => match &type_path.path.segments.last()?.arguments {
+            syn::PathArguments::AngleBracketed(arguments) => arguments.args.iter().find_map(|argument| match argument {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None
+            }),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+// Generate the registry call resolving a parameter and the description of the dependency (none for a config value)
+fn inject_parameter(input: &mut syn::FnArg, name_crate: &syn::Path) -> syn::Result<(TokenStream2, Option<TokenStream2>)> {
+    match input {
+        syn::FnArg::Typed(pat_type) => inject_value(&mut pat_type.attrs, &pat_type.ty, name_crate),
+        syn::FnArg::Receiver(receiver) => Err(syn::Error::new_spanned(receiver, "an #[inject] method builds the component and cannot take self, remove the receiver"))
+    }
+}
+
+// Generate the resolution of a parameter or a field of type `ty` from its attributes (#[named], #[config], #[default]),
+// the attributes are removed
+fn inject_value(attrs: &mut Vec<syn::Attribute>, ty: &syn::Type, name_crate: &syn::Path) -> syn::Result<(TokenStream2, Option<TokenStream2>)> {
+    let qualifier = take_qualifier(attrs)?;
+    let config_field = take_config_field(attrs)?;
+    if let Some((attr, default)) = take_default(attrs)? {
+        if qualifier.is_some() || config_field.is_some() {
+            return Err(syn::Error::new_spanned(attr, "#[default] cannot be used with #[named] or #[config]"));
+        }
+        return Ok((default, None));
+    }
+    if let Some(field) = config_field {
+        if let Some(qualifier) = qualifier {
+            return Err(syn::Error::new(qualifier.span(), "#[named] cannot be used with #[config], a config value is not a component"));
+        }
+        return Ok((quote! { #name_crate::config::inject_config(registry, std::any::type_name::<Self>(), #field)? }, None));
+    }
+    let multiple = is_type(ty, "Vec");
+    let dependency_type = if multiple {
+        generic_argument(ty).ok_or_else(|| syn::Error::new_spanned(ty, "expected the component handle received in the Vec: Vec<Arc<Mutex<dyn Trait>>>"))?
+    }
+    else {
+        ty
+    };
+    if is_type(ty, "Option") {
+        let inner = generic_argument(ty).ok_or_else(|| syn::Error::new_spanned(ty, "expected the component handle received in the Option: Option<Arc<Mutex<dyn Trait>>>"))?;
+        if ["Vec", "Lazy", "Provider", "Option"].iter().any(|name| is_type(inner, name)) {
+            return Err(syn::Error::new_spanned(inner, "an Option can only receive a component handle (Arc<Mutex<dyn Trait>>...)"));
+        }
+    }
+    let resolution = match (&qualifier, multiple) {
+        (Some(qualifier), true) => return Err(syn::Error::new(qualifier.span(), "#[named] cannot be used on a Vec which receives all the components")),
+        (Some(qualifier), false) if is_type(ty, "Option") => quote! { registry.get_optional_named_handle(#qualifier)? },
+        (None, false) if is_type(ty, "Option") => quote! { registry.get_optional_handle()? },
+        (None, true) => quote! { registry.get_all_handles()? },
+        (Some(qualifier), false) if is_type(ty, "Lazy") => quote! { registry.lazy_named(#qualifier) },
+        (None, false) if is_type(ty, "Lazy") => quote! { registry.lazy() },
+        (Some(qualifier), false) if is_type(ty, "Provider") => quote! { registry.provider_named(#qualifier) },
+        (None, false) if is_type(ty, "Provider") => quote! { registry.provider() },
+        (Some(qualifier), false) => quote! { registry.get_named_handle(#qualifier)? },
+        (None, false) => quote! { registry.get_handle()? }
+    };
+    let name = match &qualifier {
+        Some(qualifier) => quote! { Some(#qualifier) },
+        None => quote! { None }
+    };
+    Ok((
+        resolution,
+        Some(quote! { #name_crate::injection::Dependency::new::<#dependency_type>(#name, #multiple) })
+    ))
+}
+
+// Generate the *_from_reg function (*_from_reg_async for an async method) calling the method with the
+// components from the registry, and the function returning the dependencies of the method
+fn inject_method(method_data: &mut syn::ImplItemMethod, name_crate: &syn::Path) -> syn::Result<(syn::ImplItem, syn::ImplItem)> {
+    let is_async = method_data.sig.asyncness.is_some();
+    let method = &method_data.sig.ident;
     let mut new_signature = method_data.sig.clone();
-    new_signature.ident = syn::Ident::new(&format!("{}{}", method_data.sig.ident.to_string(), "_from_reg"), proc_macro2::Span::call_site());
+    let suffix = if is_async { "_from_reg_async" } else { "_from_reg" };
+    new_signature.ident = syn::Ident::new(&format!("{}{}", method_data.sig.ident.to_string(), suffix), proc_macro2::Span::call_site());
     new_signature.inputs = syn::punctuated::Punctuated::new();
-    new_signature.inputs.push(syn::parse2(quote! { registry: &mut #name_crate::injection::Registry }).unwrap());
-    let inputs = method_data.sig.inputs.clone().into_iter().map(|_| quote! { registry.get()?.clone() }).reduce(|accum, item| { quote!{ #accum, #item} }).unwrap_or(TokenStream2::new());
-    let output = quote! {
-        #new_signature {
-            Self::new(#inputs)
+    new_signature.inputs.push(syn::parse2(quote! { registry: &mut #name_crate::injection::Registry })?);
+    let (inputs, dependencies): (Vec<TokenStream2>, Vec<Option<TokenStream2>>) = method_data.sig.inputs.iter_mut()
+        .map(|input| inject_parameter(input, name_crate))
+        .collect::<syn::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let dependencies: Vec<TokenStream2> = dependencies.into_iter().flatten().collect();
+    let output = if is_async {
+        new_signature.asyncness = None;
+        let result_type = match &method_data.sig.output {
+            syn::ReturnType::Type(_, ty) => quote! { #ty },
+            syn::ReturnType::Default => quote! { () }
+        };
+        new_signature.output = syn::parse2(quote! { -> #name_crate::injection::LocalBoxFuture<'_, #result_type> })?;
+        quote! {
+            #new_signature {
+                Box::pin(async move {
+                    Self::#method(#(#inputs),*).await
+                })
+            }
+        }
+    }
+    else {
+        quote! {
+            #new_signature {
+                Self::#method(#(#inputs),*)
+            }
+        }
+    };
+    let dependencies_ident = syn::Ident::new(&format!("{}_dependencies", method_data.sig.ident), proc_macro2::Span::call_site());
+    let dependencies_output = quote! {
+        fn #dependencies_ident() -> Vec<#name_crate::injection::Dependency> where Self: Sized {
+            vec![#(#dependencies),*]
         }
     };
-    syn::ImplItem::Method(syn::parse2(output).expect("failed to parse generated function"))
+    Ok((
+        syn::ImplItem::Method(syn::parse2(output)?),
+        syn::ImplItem::Method(syn::parse2(dependencies_output)?)
+    ))
 }
 
-pub fn impl_injector(_attributes: &Vec<syn::Ident>, ast: &mut syn::Item) -> TokenStream {
-    let name_crate = crate_or_name("microservice".to_string());
+pub fn impl_injector(args: &CrateArgs, ast: &mut syn::Item) -> syn::Result<TokenStream2> {
+    let name_crate = crate_path(args.crate_path.as_ref())?;
     match ast {
         syn::Item::Impl(ref mut impl_data) => {
             let generics = impl_data.generics.clone();
             let ident = impl_data.self_ty.clone();
             let mut injection: Vec<syn::ImplItem> = Vec::new();
             let mut injection_new: Vec<syn::ImplItem> = Vec::new();
+            // Constructors selected by profile: the profile, the *_from_reg function and the *_dependencies function
+            let mut profiles: Vec<(syn::LitStr, syn::Ident, syn::Ident)> = Vec::new();
             for item in impl_data.items.iter_mut() {
                 match item {
                     syn::ImplItem::Method(ref mut method_data) => {
-                        let mut found = false;
-                        method_data.attrs.retain(|attr| {
-                            if quote!(#attr).to_string() == "#[inject]".to_string() {
-                                found = true;
-                                false
-                            }
-                            else {
-                                true
+                        if let Some(profile) = take_inject(&mut method_data.attrs)? {
+                            let (mut from_reg, mut dependencies) = inject_method(method_data, &name_crate)?;
+                            if let Some(profile) = profile {
+                                if method_data.sig.asyncness.is_some() {
+                                    return Err(syn::Error::new(profile.span(), "a profile constructor cannot be async, register the component with register_async"));
+                                }
+                                if profiles.iter().any(|(other, _, _)| other.value() == profile.value()) {
+                                    return Err(syn::Error::new(profile.span(), "another #[inject] method is the constructor of this profile"));
+                                }
+                                let method = &method_data.sig.ident;
+                                profiles.push((
+                                    profile,
+                                    syn::Ident::new(&format!("{}_from_reg", method), proc_macro2::Span::call_site()),
+                                    syn::Ident::new(&format!("{}_dependencies", method), proc_macro2::Span::call_site())
+                                ));
                             }
-                        });
-                        if found {
                             if method_data.sig.ident.to_string() == "new".to_string() {
-                                injection_new.push(inject_method(&method_data));
+                                if method_data.sig.asyncness.is_some() {
+                                    // The synchronous constructor reports that register_async must be used
+                                    injection_new.push(syn::parse2(quote! {
+                                        fn new_from_reg(_registry: &mut #name_crate::injection::Registry) -> #name_crate::error::Result<Self> where Self: Sized {
+                                            Err(#name_crate::injection::RegistryError::AsyncConstructorError { name: std::any::type_name::<Self>() }.into())
+                                        }
+                                    })?);
+                                }
+                                injection_new.push(from_reg);
+                                injection_new.push(dependencies);
                             }
                             else {
-                                injection.push(inject_method(&method_data));
+                                // Visible as the method to be given to register_with
+                                for item in [&mut from_reg, &mut dependencies] {
+                                    if let syn::ImplItem::Method(generated) = item {
+                                        generated.vis = method_data.vis.clone();
+                                    }
+                                }
+                                injection.push(from_reg);
+                                injection.push(dependencies);
                             }
                         }
                     },
@@ -131,14 +587,68 @@ pub fn impl_injector(_attributes: &Vec<syn::Ident>, ast: &mut syn::Item) -> Toke
                 }
             };
             impl_data.items.append(&mut injection);
+            if !profiles.is_empty() {
+                let arms = profiles.iter().map(|(profile, constructor, dependencies)| quote! {
+                    #profile => Some((Self::#constructor, Self::#dependencies())),
+                });
+                injection_new.push(syn::parse2(quote! {
+                    fn profile_constructor(profile: &str) -> Option<(#name_crate::injection::Constructor<Self>, Vec<#name_crate::injection::Dependency>)> where Self: Sized {
+                        match profile {
+                            #(#arms)*
+                            _ => None
+                        }
+                    }
+                })?);
+            }
             let output = quote! {
 		#ast
                 impl #generics #name_crate::injection::Injection for #ident {
                     #( #injection_new )*
                 }
             };
-            proc_macro::TokenStream::from(output)
+            Ok(output)
         }
-        _ => panic!("Injector is only usable on impl blocks")
+        _ => Err(syn::Error::new_spanned(ast, "#[injector] can only be used on an impl block"))
     }
 }
+
+// Generate the Injection implementation of a structure whose fields are resolved from the registry
+pub fn impl_inject(ast: &syn::DeriveInput) -> syn::Result<TokenStream2> {
+    let args = match ast.attrs.iter().find(|attr| attr.path.is_ident("injection")) {
+        Some(attr) => attr.parse_args::<CrateArgs>()?,
+        None => CrateArgs { crate_path: None }
+    };
+    let name_crate = crate_path(args.crate_path.as_ref())?;
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let fields = match &ast.data {
+        syn::Data::Struct(struct_data) => &struct_data.fields,
+        _ => return Err(syn::Error::new(ident.span(), "#[derive(Inject)] can only be used on a struct"))
+    };
+    let (values, dependencies): (Vec<TokenStream2>, Vec<Option<TokenStream2>>) = fields.iter()
+        .map(|field| inject_value(&mut field.attrs.clone(), &field.ty, &name_crate))
+        .collect::<syn::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let dependencies: Vec<TokenStream2> = dependencies.into_iter().flatten().collect();
+    let construction = match fields {
+        syn::Fields::Named(_) => {
+            let names = fields.iter().map(|field| &field.ident);
+            quote! { Self { #(#names: #values),* } }
+        },
+        syn::Fields::Unnamed(_) => quote! { Self(#(#values),*) },
+        syn::Fields::Unit => quote! { Self }
+    };
+    let output = quote! {
+        impl #impl_generics #name_crate::injection::Injection for #ident #ty_generics #where_clause {
+            fn new_from_reg(registry: &mut #name_crate::injection::Registry) -> #name_crate::error::Result<Self> where Self: Sized {
+                Ok(#construction)
+            }
+
+            fn new_dependencies() -> Vec<#name_crate::injection::Dependency> where Self: Sized {
+                vec![#(#dependencies),*]
+            }
+        }
+    };
+    Ok(output)
+}
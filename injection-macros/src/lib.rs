@@ -1,29 +1,34 @@
 use proc_macro::TokenStream;
-use proc_macro2::{TokenStream as TokenStream2};
 
 use implementations::*;
 mod implementations;
 
 #[proc_macro_attribute]
 pub fn injectable(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let attributes: Vec<syn::Ident> = syn::parse_macro_input!(attr with syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated)
-        .into_iter()
-        .collect();
-    let mut ast: syn::Item = syn::parse2(TokenStream2::from(item)).expect("Failed to parse Input");
+    let args = syn::parse_macro_input!(attr as InjectableArgs);
+    let mut ast = syn::parse_macro_input!(item as syn::Item);
 
-    impl_injectable(&attributes, &mut ast)
+    impl_injectable(&args, &mut ast).unwrap_or_else(|error| error.to_compile_error()).into()
 }
 
 #[proc_macro_attribute]
-pub fn inject(_attr: TokenStream, _item: TokenStream) -> TokenStream {
-    panic!("#[inject] must be called in an impl block with the attribute #[injector]")
+pub fn inject(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    // The item is kept to report only the misplaced attribute
+    let mut output = syn::Error::new(proc_macro2::Span::call_site(), "#[inject] must be used on a method of an impl block with the attribute #[injector]")
+        .to_compile_error();
+    output.extend(proc_macro2::TokenStream::from(item));
+    output.into()
 }
 
 #[proc_macro_attribute]
 pub fn injector(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let attributes: Vec<syn::Ident> = syn::parse_macro_input!(attr with syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated)
-        .into_iter()
-        .collect();
-    let mut ast: syn::Item = syn::parse2(TokenStream2::from(item)).expect("Failed to parse Input");
-    impl_injector(&attributes, &mut ast)
+    let args = syn::parse_macro_input!(attr as CrateArgs);
+    let mut ast = syn::parse_macro_input!(item as syn::Item);
+    impl_injector(&args, &mut ast).unwrap_or_else(|error| error.to_compile_error()).into()
+}
+
+#[proc_macro_derive(Inject, attributes(injection, named, config, default))]
+pub fn derive_inject(item: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(item as syn::DeriveInput);
+    impl_inject(&ast).unwrap_or_else(|error| error.to_compile_error()).into()
 }
@@ -1,9 +1,11 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::ffi::OsString;
 use serde::Deserialize;
 pub use serde_yaml::Value;
 
 use crate::error::*;
+use crate::injection::{Registry, RegistryError};
+use crate::Err;
 use crate::service::Service;
 use crate::injectable;
 
@@ -25,7 +27,10 @@ pub enum ConfigError {
     FieldNotFound { field: String },
 
     #[error("Config - Unable to convert hostname in string: {hostname:?}")]
-    HostnameConversionFailed { hostname: OsString }
+    HostnameConversionFailed { hostname: OsString },
+
+    #[error("Config - Unable to inject the field {field} in the component {component}")]
+    InjectionError { component: String, field: String }
 
 }
 
@@ -35,16 +40,112 @@ pub trait Config: Service {
     fn get(&self, field: &str) -> Result<Value>;
 }
 
-pub fn get_from_config<'de, T>(conf: Arc<Mutex<dyn Config>>, field: &str) -> Result<T> where T: Deserialize<'de> + Sized {
-    let value: Value = conf.lock().unwrap().get(field)?;
+/// Config handle read by get_from_config whatever the storage of the config component
+pub trait ConfigHandle {
+    fn get_value(&self, field: &str) -> Result<Value>;
+}
+
+impl ConfigHandle for Arc<Mutex<dyn Config>> {
+    fn get_value(&self, field: &str) -> Result<Value> {
+        self.lock().unwrap().get(field)
+    }
+}
+
+impl ConfigHandle for Arc<RwLock<dyn Config>> {
+    fn get_value(&self, field: &str) -> Result<Value> {
+        self.read().unwrap().get(field)
+    }
+}
+
+impl ConfigHandle for Arc<dyn Config> {
+    fn get_value(&self, field: &str) -> Result<Value> {
+        self.get(field)
+    }
+}
+
+pub fn get_from_config<'de, T, C>(conf: C, field: &str) -> Result<T> where T: Deserialize<'de> + Sized, C: ConfigHandle {
+    let value: Value = conf.get_value(field)?;
     T::deserialize(value.clone()).map_err(|source| (ConfigError::DeserializationError { source, field: field.to_string(), value: value.clone() }).into())
 }
 
-pub fn get_or_from_config<'de, T>(conf: Arc<Mutex<dyn Config>>, field: &str, default: T) -> T where T: Deserialize<'de> + Sized {
-    if let Ok(value) = conf.lock().unwrap().get(field) {
+pub fn get_or_from_config<'de, T, C>(conf: C, field: &str, default: T) -> T where T: Deserialize<'de> + Sized, C: ConfigHandle {
+    if let Ok(value) = conf.get_value(field) {
         T::deserialize(value.clone()).unwrap_or(default)
     }
     else {
         default
     }
 }
+
+/// Call `f` with the Config registered in the registry whatever its storage, None if there is no Config
+pub fn with_config<R, F>(registry: &mut Registry, f: F) -> Option<R> where F: FnOnce(&dyn Config) -> R {
+    if let Ok(conf) = registry.get::<dyn Config>() {
+        return Some(f(&*conf.lock().unwrap()));
+    }
+    if let Ok(conf) = registry.get_rw::<dyn Config>() {
+        return Some(f(&*conf.read().unwrap()));
+    }
+    registry.get_shared::<dyn Config>().ok().map(|conf| f(&*conf))
+}
+
+/// Read the value of a parameter declared with #[config("field")] in an #[inject] method
+pub fn inject_config<'de, T>(registry: &mut Registry, component: &str, field: &str) -> Result<T> where T: Deserialize<'de> + Sized {
+    let value = match with_config(registry, |config| config.get(field)) {
+        Some(value) => value,
+        None => Err!(RegistryError::NoComponentError { name: std::any::type_name::<dyn Config>() })
+    };
+    value
+        .and_then(|value| T::deserialize(value.clone()).map_err(|source| (ConfigError::DeserializationError { source, field: field.to_string(), value }).into()))
+        .context(ConfigError::InjectionError { component: component.to_string(), field: field.to_string() })
+}
+
+/// Condition on a config field used by `#[injectable(Trait, when = "...")]`:
+/// `field` (present and not false), `field == 'value'` or `field != 'value'`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Present(String),
+    Equal(String, String),
+    NotEqual(String, String)
+}
+
+impl Condition {
+    pub fn parse(condition: &str) -> Option<Self> {
+        let operand = |value: &str| {
+            let value = value.trim();
+            value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')).unwrap_or(value).to_string()
+        };
+        let field = |field: &str| Some(field.trim().to_string()).filter(|field| !field.is_empty() && !field.contains(char::is_whitespace));
+        if let Some((left, right)) = condition.split_once("!=") {
+            Some(Condition::NotEqual(field(left)?, operand(right)))
+        }
+        else if let Some((left, right)) = condition.split_once("==") {
+            Some(Condition::Equal(field(left)?, operand(right)))
+        }
+        else {
+            Some(Condition::Present(field(condition)?))
+        }
+    }
+
+    pub fn evaluate(&self, config: &dyn Config) -> bool {
+        // Scalar values are compared with their text
+        let text = |field: &str| match config.get(field) {
+            Ok(Value::String(value)) => Some(value),
+            Ok(Value::Bool(value)) => Some(value.to_string()),
+            Ok(Value::Number(value)) => Some(value.to_string()),
+            _ => None
+        };
+        match self {
+            Condition::Present(field) => !matches!(config.get(field), Err(_) | Ok(Value::Null) | Ok(Value::Bool(false))),
+            Condition::Equal(field, expected) => text(field).as_ref() == Some(expected),
+            Condition::NotEqual(field, expected) => text(field).as_ref() != Some(expected)
+        }
+    }
+}
+
+/// Evaluate a condition against the Config of the registry, false without Config or if the condition is invalid
+pub fn condition_holds(registry: &mut Registry, condition: &str) -> bool {
+    match Condition::parse(condition) {
+        Some(condition) => with_config(registry, |config| condition.evaluate(config)).unwrap_or(false),
+        None => false
+    }
+}
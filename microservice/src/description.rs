@@ -0,0 +1,92 @@
+use serde::Serialize;
+use std::fmt::Write;
+
+use crate::error::*;
+use crate::injection::Lifetime;
+
+/// Content of a registry returned by `Registry::describe`: its components in registration order, then those of its parents
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegistryDescription {
+    pub components: Vec<ComponentDescription>
+}
+
+/// Registration of a component and the handle types under which it can be resolved
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentDescription {
+    /// Index of the component in the description
+    pub id: usize,
+    pub component: &'static str,
+    pub name: Option<String>,
+    pub lifetime: Lifetime,
+    /// 0 for the described registry, 1 for its parent scope...
+    pub scope: usize,
+    pub provides: Vec<&'static str>,
+    pub dependencies: Vec<DependencyDescription>
+}
+
+/// Constructor dependency of a component with the indexes of the components providing it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DependencyDescription {
+    pub type_name: &'static str,
+    pub name: Option<&'static str>,
+    pub multiple: bool,
+    pub deferred: bool,
+    pub optional: bool,
+    pub providers: Vec<usize>
+}
+
+impl RegistryDescription {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Graphviz graph with an edge from each component to the components it depends on,
+    /// deferred dependencies (Lazy, Provider) are dashed
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph registry {\n    node [shape=box];\n");
+        for component in self.components.iter() {
+            let mut label = short_type_name(component.component);
+            if let Some(name) = &component.name {
+                let _ = write!(label, " ({})", name);
+            }
+            let _ = write!(label, "\\n{:?}", component.lifetime);
+            for provided in component.provides.iter() {
+                let _ = write!(label, "\\n{}", short_type_name(provided));
+            }
+            let _ = writeln!(dot, "    c{} [label=\"{}\"];", component.id, label.replace('"', "\\\""));
+        }
+        for component in self.components.iter() {
+            for dependency in component.dependencies.iter() {
+                for provider in dependency.providers.iter() {
+                    let style = if dependency.deferred { " [style=dashed]" } else { "" };
+                    let _ = writeln!(dot, "    c{} -> c{}{};", component.id, provider, style);
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// Remove the module paths of a type name: alloc::sync::Arc<dyn a::B> -> Arc<dyn B>
+fn short_type_name(name: &str) -> String {
+    let mut short = String::new();
+    let mut segment = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        }
+        else if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        }
+        else {
+            short.push_str(&segment);
+            segment.clear();
+            short.push(c);
+        }
+    }
+    short.push_str(&segment);
+    short
+}
@@ -1,43 +1,360 @@
 use std::any::{Any, TypeId, type_name};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::fmt::Debug;
 use injection_macros::*;
+use serde::Serialize;
 
+pub use futures::future::LocalBoxFuture;
+
+use super::config::{self, Config};
+use super::description::*;
 use super::error::*;
-use super::Err;
+use super::{Err, trace};
 
 /// Errors generated using a Registry
-#[derive(Error, PartialEq, Debug)]
-pub enum RegistryError<'a> {
-    #[error("Multiple components correspond to dependency {name}, inject dependencies manually")]
-    MultipleComponentsError { name: &'a str },
+#[derive(Error, PartialEq, Clone, Debug)]
+pub enum RegistryError {
+    #[error("Multiple components correspond to dependency {name}, register them with a name and use a qualifier")]
+    MultipleComponentsError { name: &'static str },
 
     #[error("No component correspond to dependency {name}")]
-    NoComponentError { name: &'a str },
+    NoComponentError { name: &'static str },
+
+    #[error("Multiple components correspond to dependency {name} with the qualifier {qualifier}")]
+    MultipleNamedComponentsError { name: &'static str, qualifier: String },
+
+    #[error("No component correspond to dependency {name} with the qualifier {qualifier}")]
+    NoNamedComponentError { name: &'static str, qualifier: String },
+
+    #[error("Component {component} depends on {dependency} which is not registered")]
+    MissingDependencyError { component: &'static str, dependency: String },
+
+    #[error("Circular dependency between components: {path}")]
+    CircularDependencyError { path: String },
+
+    #[error("Component {name} does not correspond to the requested type")]
+    ComponentTypeError { name: &'static str },
+
+    #[error("Component {name} new with injection is not implemented and must be called manually: use #[inject]")]
+    NotImplemented { name: &'static str },
+
+    #[error("Component {name} has an async constructor and must be registered with register_async")]
+    AsyncConstructorError { name: &'static str },
+
+    #[error("The registry of the lazy dependency {name} has been dropped")]
+    RegistryDroppedError { name: &'static str },
+
+    #[error("The components of {name} are used by the microservice with the default storage Arc<Mutex<_>>, remove their storage option")]
+    StorageError { name: &'static str },
+
+    #[error("No instance or singleton registered in this registry corresponds to the handle {name}")]
+    UnknownHandleError { name: &'static str },
 
-    #[error("Component type error during conversion")]
-    ComponentTypeError,
+    #[error("Component {name} is registered only when {condition}, use declare or register_if")]
+    ConditionError { name: &'static str, condition: &'static str },
 
-    #[error("Component new with injection is not implemented and must be called manually: use #[inject]")]
-    NotImplemented
+    /// Context added to the error of a constructor, the cause is the error returned by the constructor
+    #[error("Unable to construct {component} (construction chain: {chain})")]
+    ConstructorError { component: &'static str, chain: String }
 }
 
 /// If we want to use a clean architecture of the object classes, Component is the Base trait at the root
 /// of each injectable objects which can be registered in a Registry
 #[injectable]
 pub trait Component {
-    fn register(component_ref: Arc<Mutex<Self>>, registry: &mut Registry) where Self: Sized + 'static;
     fn struct_impl_trait<T>() -> bool where T: ?Sized + 'static, Self: Sized + 'static;
     fn is_impl_trait<T>(&self) -> bool where T: ?Sized + 'static, Self: Sized + 'static {
          Self::struct_impl_trait::<T>()
     }
 }
 
+/// Handle under which the registry shares a component, chosen with the `storage` option of #[injectable]:
+/// `Arc<Mutex<T>>` by default, `Arc<RwLock<T>>` with `storage = rwlock` and `Arc<T>` with `storage = shared`
+pub trait ComponentStorage {
+    type Handle: Clone + 'static;
+    /// `get` prefers a primary component (`#[injectable(Trait, primary)]`), then the highest priority (`priority = N`)
+    const PRIMARY: bool = false;
+    const PRIORITY: i32 = 0;
+    /// Config condition required to register the component (`when = "..."`), see `config::Condition`
+    const CONDITION: Option<&'static str> = None;
+    fn into_handle(self) -> Self::Handle;
+    fn register(entry: RegistryEntry<Self::Handle>, registry: &mut Registry) -> Result<()> where Self: Sized + 'static;
+}
+
 /// Injection is the implementation of the constructor of the class which is automatically called when dependency injection is used
 pub trait Injection {
     fn new_from_reg(_registry: &mut Registry) -> Result<Self> where Self: Sized {
-        Err!(RegistryError::NotImplemented)
+        Err!(RegistryError::NotImplemented { name: type_name::<Self>() })
+    }
+
+    /// Asynchronous constructor generated for `#[inject] async fn new`, calls `new_from_reg` by default
+    fn new_from_reg_async(registry: &mut Registry) -> LocalBoxFuture<'_, Result<Self>> where Self: Sized + 'static {
+        Box::pin(async move {
+            Self::new_from_reg(registry)
+        })
+    }
+
+    /// Dependencies resolved by `new_from_reg`, used to order the construction of declared components
+    fn new_dependencies() -> Vec<Dependency> where Self: Sized {
+        Vec::new()
+    }
+
+    /// Constructor generated for the `#[inject(profile = "...")]` method of `profile`, with its dependencies
+    fn profile_constructor(_profile: &str) -> Option<(Constructor<Self>, Vec<Dependency>)> where Self: Sized {
+        None
+    }
+}
+
+/// Reference to a component as received by an injected parameter
+pub trait ComponentRef {
+    /// Handle under which the component is looked up in the registry
+    type Handle: 'static;
+    /// The component is resolved after the construction and does not constrain the construction order
+    const DEFERRED: bool = false;
+    /// The parameter receives None when no component is registered
+    const OPTIONAL: bool = false;
+}
+
+impl<T> ComponentRef for Arc<T> where T: ?Sized + 'static {
+    type Handle = Arc<T>;
+}
+
+impl<R> ComponentRef for Option<R> where R: ComponentRef {
+    type Handle = R::Handle;
+    const DEFERRED: bool = R::DEFERRED;
+    const OPTIONAL: bool = true;
+}
+
+impl<T> ComponentRef for Lazy<T> where T: ?Sized + 'static {
+    type Handle = Arc<Mutex<T>>;
+    const DEFERRED: bool = true;
+}
+
+impl<T> ComponentRef for Provider<T> where T: ?Sized + 'static {
+    type Handle = Arc<Mutex<T>>;
+    const DEFERRED: bool = true;
+}
+
+/// Dependency of a constructor on a type of the registry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+    pub name: Option<&'static str>,
+    pub multiple: bool,
+    pub deferred: bool,
+    pub optional: bool
+}
+
+impl Dependency {
+    pub fn new<R>(name: Option<&'static str>, multiple: bool) -> Self where R: ComponentRef {
+        Self {
+            type_id: TypeId::of::<R::Handle>(),
+            type_name: type_name::<R::Handle>(),
+            name,
+            multiple,
+            deferred: R::DEFERRED,
+            optional: R::OPTIONAL
+        }
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "{} named {}", self.type_name, name),
+            None => write!(f, "{}", self.type_name)
+        }
+    }
+}
+
+/// Lifetime of the components provided by a registration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Lifetime {
+    /// One instance shared by every resolution
+    Singleton,
+    /// A new instance built with `new_from_reg` on every resolution
+    Transient,
+    /// One instance for each registry scope in which the component is resolved
+    Scoped
+}
+
+impl Default for Lifetime {
+    fn default() -> Self {
+        Lifetime::Singleton
+    }
+}
+
+// Counter used to identify registrations (and the scoped values) across a registry and all its scopes
+static NEXT_REGISTRATION_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Informations shared by all the entries created by the registration of one component
+#[derive(Debug)]
+pub struct Registration {
+    pub id: usize,
+    pub component: &'static str,
+    pub name: Option<String>,
+    pub lifetime: Lifetime,
+    pub dependencies: Vec<Dependency>,
+    pub primary: bool,
+    pub priority: i32,
+    /// Handle types for which the component hides the other registrations, see `Registry::override_with`
+    pub overrides: Vec<TypeId>
+}
+
+impl Registration {
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_REGISTRATION_ID.fetch_add(1, Ordering::Relaxed),
+            component: "",
+            name: None,
+            lifetime: Lifetime::default(),
+            dependencies: Vec::new(),
+            primary: false,
+            priority: 0,
+            overrides: Vec::new()
+        }
+    }
+
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            ..Self::new()
+        }
+    }
+
+    pub fn with_lifetime(lifetime: Lifetime) -> Self {
+        Self {
+            lifetime,
+            ..Self::new()
+        }
+    }
+}
+
+impl Default for Registration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Function building the handle of a component from the registry in which it is resolved
+pub type Factory<H> = Rc<dyn Fn(&mut Registry) -> Result<H>>;
+
+/// Constructor generated for an `#[inject]` method: `T::new_from_reg`, `T::new_in_memory_from_reg`...
+pub type Constructor<T> = fn(&mut Registry) -> Result<T>;
+
+/// Function returning the handle of a singleton once it has been built by its factory
+pub type Built<H> = Rc<dyn Fn() -> Option<H>>;
+
+/// Function wrapping the handle of a component, see `Registry::decorate`
+pub type Decorator<H> = Rc<dyn Fn(H) -> H>;
+
+/// How the handle of an entry is obtained
+pub enum Binding<H> {
+    Instance(H),
+    Factory(Factory<H>),
+    /// Singleton built by its factory on its first resolution (`declare`, `register_factory`)
+    Singleton(Factory<H>, Built<H>)
+}
+
+impl<H> Clone for Binding<H> where H: Clone {
+    fn clone(&self) -> Self {
+        match self {
+            Binding::Instance(component) => Binding::Instance(component.clone()),
+            Binding::Factory(factory) => Binding::Factory(factory.clone()),
+            Binding::Singleton(factory, built) => Binding::Singleton(factory.clone(), built.clone())
+        }
+    }
+}
+
+/// Component binding stored in a registry vector with its registration
+pub struct RegistryEntry<H> {
+    pub registration: Rc<Registration>,
+    pub binding: Binding<H>
+}
+
+impl<H> RegistryEntry<H> where H: Clone + 'static {
+    pub fn new(binding: Binding<H>, registration: Registration) -> Self {
+        Self {
+            registration: Rc::new(registration),
+            binding
+        }
+    }
+
+    /// Convert the component handle, used to register a component with each of its traits
+    pub fn map<U>(self, f: fn(H) -> U) -> RegistryEntry<U> where U: 'static {
+        let binding = match self.binding {
+            Binding::Instance(component) => Binding::Instance(f(component)),
+            Binding::Factory(factory) => Binding::Factory(Rc::new(move |registry: &mut Registry| factory(registry).map(f))),
+            Binding::Singleton(factory, built) => Binding::Singleton(
+                Rc::new(move |registry: &mut Registry| factory(registry).map(f)),
+                Rc::new(move || built().map(f))
+            )
+        };
+        RegistryEntry {
+            registration: self.registration,
+            binding
+        }
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.registration.name.as_deref() == Some(name)
+    }
+
+    /// Wrap the handle of the entry with a decorator, applied once for a singleton,
+    /// once per scope for a scoped component and on each resolution for a transient one
+    pub fn decorate(self, decorator: Decorator<H>) -> Self {
+        let binding = match self.binding {
+            Binding::Instance(component) => Binding::Instance(decorator(component)),
+            Binding::Factory(factory) | Binding::Singleton(factory, _) => {
+                let decorated = move |registry: &mut Registry| factory(registry).map(|component| decorator(component));
+                match self.registration.lifetime {
+                    Lifetime::Singleton => {
+                        let (factory, built) = cached(self.registration.component, decorated);
+                        Binding::Singleton(factory, built)
+                    },
+                    Lifetime::Transient => Binding::Factory(Rc::new(decorated)),
+                    Lifetime::Scoped => {
+                        let id = NEXT_REGISTRATION_ID.fetch_add(1, Ordering::Relaxed);
+                        let decorated = Rc::new(decorated);
+                        Binding::Factory(Rc::new(move |registry: &mut Registry| {
+                            let decorated = decorated.clone();
+                            registry.scoped_value(id, move |registry: &mut Registry| decorated(registry))
+                        }))
+                    }
+                }
+            }
+        };
+        RegistryEntry {
+            registration: self.registration,
+            binding
+        }
+    }
+
+    // Rank of the entry when several entries can be resolved, the highest is preferred
+    fn rank(&self) -> (bool, i32) {
+        (self.registration.primary, self.registration.priority)
+    }
+
+    /// Return the component of the entry, building it if needed
+    pub fn resolve(&self, registry: &mut Registry) -> Result<H> {
+        match &self.binding {
+            Binding::Instance(component) => Ok(component.clone()),
+            Binding::Factory(factory) | Binding::Singleton(factory, _) => factory(registry)
+        }
+    }
+}
+
+impl<H> Clone for RegistryEntry<H> where H: Clone {
+    fn clone(&self) -> Self {
+        Self {
+            registration: self.registration.clone(),
+            binding: self.binding.clone()
+        }
     }
 }
 
@@ -45,10 +362,14 @@ pub trait Injection {
 pub trait RegistryVec {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn registrations(&self) -> Vec<Rc<Registration>>;
+    fn remove_registration(&mut self, id: usize);
+    fn is_empty(&self) -> bool;
+    fn handle_name(&self) -> &'static str;
 }
 
 
-impl<T> RegistryVec for Mutex<Vec<Arc<Mutex<T>>>> where T: ?Sized + 'static {
+impl<H> RegistryVec for Vec<RegistryEntry<H>> where H: 'static {
     fn as_any(&self) -> &dyn Any {
         self as &dyn Any
     }
@@ -56,80 +377,762 @@ impl<T> RegistryVec for Mutex<Vec<Arc<Mutex<T>>>> where T: ?Sized + 'static {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self as &mut dyn Any
     }
+
+    fn registrations(&self) -> Vec<Rc<Registration>> {
+        self.iter().map(|entry| entry.registration.clone()).collect()
+    }
+
+    fn remove_registration(&mut self, id: usize) {
+        self.retain(|entry| entry.registration.id != id);
+    }
+
+    fn is_empty(&self) -> bool {
+        Vec::is_empty(self)
+    }
+
+    fn handle_name(&self) -> &'static str {
+        type_name::<H>()
+    }
+}
+
+/// Component resolved from the registry on its first use, used to break construction cycles.
+/// It refers to the registry which is bound to its thread: a component holding it is neither Send nor Sync.
+/// Only the components of the default storage (`Arc<Mutex<T>>`) are resolved.
+pub struct Lazy<T> where T: ?Sized {
+    scope: Weak<Scope>,
+    name: Option<String>,
+    component: RefCell<Option<Arc<Mutex<T>>>>
+}
+
+impl<T> Lazy<T> where T: ?Sized + 'static {
+    /// Resolve the component on the first call and return the same component on the next ones
+    pub fn get(&self) -> Result<Arc<Mutex<T>>> {
+        if let Some(component) = &*self.component.borrow() {
+            return Ok(component.clone());
+        }
+        let component = resolve_from_scope::<T>(&self.scope, self.name.as_deref())?;
+        *self.component.borrow_mut() = Some(component.clone());
+        Ok(component)
+    }
+}
+
+/// Factory resolving the component from the registry on each call, a transient component is built each time.
+/// Like `Lazy`, a component holding it is neither Send nor Sync.
+pub struct Provider<T> where T: ?Sized {
+    scope: Weak<Scope>,
+    name: Option<String>,
+    component: std::marker::PhantomData<fn() -> Arc<Mutex<T>>>
+}
+
+impl<T> Provider<T> where T: ?Sized + 'static {
+    pub fn get(&self) -> Result<Arc<Mutex<T>>> {
+        resolve_from_scope::<T>(&self.scope, self.name.as_deref())
+    }
+}
+
+// Resolve a component in a registry scope kept by a lazy dependency
+fn resolve_from_scope<T>(scope: &Weak<Scope>, name: Option<&str>) -> Result<Arc<Mutex<T>>> where T: ?Sized + 'static {
+    let scope = match scope.upgrade() {
+        Some(scope) => scope,
+        None => return Err!(RegistryError::RegistryDroppedError { name: type_name::<T>() })
+    };
+    let mut registry = Registry { scope };
+    match name {
+        Some(name) => registry.get_named::<T>(name),
+        None => registry.get::<T>()
+    }
 }
 
+// Factory building the handle on its first call and returning the same handle on the next ones,
+// with the function returning the handle once built
+fn cached<H, F>(name: &'static str, build: F) -> (Factory<H>, Built<H>) where H: Clone + 'static, F: Fn(&mut Registry) -> Result<H> + 'static {
+    let instance: Rc<RefCell<Option<H>>> = Rc::new(RefCell::new(None));
+    let built = instance.clone();
+    let building = Cell::new(false);
+    let factory: Factory<H> = Rc::new(move |registry: &mut Registry| {
+        if let Some(component) = &*instance.borrow() {
+            return Ok(component.clone());
+        }
+        if building.replace(true) {
+            return Err!(RegistryError::CircularDependencyError { path: name.to_string() });
+        }
+        let component = build(registry);
+        building.set(false);
+        let component = component?;
+        *instance.borrow_mut() = Some(component.clone());
+        Ok(component)
+    });
+    (factory, Rc::new(move || built.borrow().clone()))
+}
+
+// Construct a singleton with a registry for the scope owning its registration, whichever scope resolves it
+// first: the registrations of a child scope must not leak into a singleton shared with its parent
+fn in_owner_scope<H, F>(scope: &Rc<Scope>, name: &'static str, build: F) -> impl Fn(&mut Registry) -> Result<H> where F: Fn(&mut Registry) -> Result<H> {
+    // A weak reference as the factory is stored in the scope
+    let scope = Rc::downgrade(scope);
+    move |_: &mut Registry| {
+        let scope = match scope.upgrade() {
+            Some(scope) => scope,
+            None => return Err!(RegistryError::RegistryDroppedError { name })
+        };
+        build(&mut Registry { scope })
+    }
+}
+
+// Component in the chain of the components under construction of the root scope, removed from the chain when dropped
+struct Constructing {
+    root: Rc<Scope>
+}
+
+impl Constructing {
+    // Add a component to the chain, a component already in the chain depends on itself
+    fn start(scope: &Rc<Scope>, component: &'static str) -> Result<Self> {
+        let root = Scope::root(scope);
+        let cycle = root.constructing.borrow().iter().position(|constructing| *constructing == component);
+        if let Some(start) = cycle {
+            let mut path = root.constructing.borrow()[start..].to_vec();
+            path.push(component);
+            return Err!(RegistryError::CircularDependencyError { path: path.join(" -> ") });
+        }
+        root.constructing.borrow_mut().push(component);
+        Ok(Self { root })
+    }
+
+    fn chain(&self) -> String {
+        self.root.constructing.borrow().join(" -> ")
+    }
+}
+
+impl Drop for Constructing {
+    fn drop(&mut self) {
+        self.root.constructing.borrow_mut().pop();
+    }
+}
+
+// Add the failing component and the construction chain to a constructor error, only for the innermost constructor
+fn constructor_error(error: anyhow::Error, component: &'static str, chain: String) -> anyhow::Error {
+    if let Some(RegistryError::ConstructorError { .. }) = error.downcast_ref::<RegistryError>() {
+        return error;
+    }
+    error.context(RegistryError::ConstructorError { component, chain })
+}
+
+// Function constructing a declared component
+type Build = Rc<dyn Fn(&mut Registry) -> Result<()>>;
+
+// Component declared in a registry and waiting for Registry::build to be constructed
+struct Pending {
+    registration: Rc<Registration>,
+    build: Build
+}
+
+
+// Storage of a registry level, shared with the child scopes which fall back on it
+struct Scope {
+    registries: RefCell<HashMap<TypeId, Box<dyn RegistryVec>>>,
+    scoped_instances: RefCell<HashMap<usize, Box<dyn Any>>>,
+    decorators: RefCell<HashMap<TypeId, Box<dyn Any>>>,
+    pending: RefCell<Vec<Pending>>,
+    // Components under construction, only used in the root scope
+    constructing: RefCell<Vec<&'static str>>,
+    parent: Option<Rc<Scope>>
+}
+
+impl Scope {
+    fn new(parent: Option<Rc<Scope>>) -> Self {
+        Self {
+            registries: RefCell::new(HashMap::new()),
+            scoped_instances: RefCell::new(HashMap::new()),
+            decorators: RefCell::new(HashMap::new()),
+            pending: RefCell::new(Vec::new()),
+            constructing: RefCell::new(Vec::new()),
+            parent
+        }
+    }
+
+    // Root scope shared by a registry and all its scopes
+    fn root(scope: &Rc<Scope>) -> Rc<Scope> {
+        match &scope.parent {
+            Some(parent) => Scope::root(parent),
+            None => scope.clone()
+        }
+    }
+
+    // Registrations providing a type in this scope or, if there is none, in the nearest parent:
+    // those named `qualifier`, else the overriding ones if any
+    fn registrations(&self, id: &TypeId, qualifier: Option<&str>) -> Vec<Rc<Registration>> {
+        if let Some(registry_entry) = self.registries.borrow().get(id) {
+            let registrations = registry_entry.registrations();
+            if qualifier.is_some() {
+                return registrations.into_iter().filter(|registration| registration.name.as_deref() == qualifier).collect();
+            }
+            let overrides: Vec<Rc<Registration>> = registrations.iter().filter(|registration| registration.overrides.contains(id)).cloned().collect();
+            return if overrides.is_empty() { registrations } else { overrides };
+        }
+        match &self.parent {
+            Some(parent) => parent.registrations(id, qualifier),
+            None => Vec::new()
+        }
+    }
+
+    // Entries registered for a type in this scope or, if there is none, in the nearest parent:
+    // those named `qualifier`, else the overriding ones if any (an override does not hide the named components)
+    fn entries<H>(&self, qualifier: Option<&str>) -> Result<Vec<RegistryEntry<H>>> where H: Clone + 'static {
+        let id = TypeId::of::<H>();
+        if let Some(registry_entry) = self.registries.borrow().get(&id) {
+            if let Some(registry_vec) = registry_entry.as_any().downcast_ref::<Vec<RegistryEntry<H>>>() {
+                if let Some(qualifier) = qualifier {
+                    return Ok(registry_vec.iter().filter(|entry| entry.is_named(qualifier)).cloned().collect());
+                }
+                let overrides: Vec<RegistryEntry<H>> = registry_vec.iter().filter(|entry| entry.registration.overrides.contains(&id)).cloned().collect();
+                return Ok(if overrides.is_empty() { registry_vec.clone() } else { overrides });
+            }
+            else {
+                return Err!(RegistryError::ComponentTypeError { name: type_name::<H>() });
+            }
+        }
+        match &self.parent {
+            Some(parent) => parent.entries::<H>(qualifier),
+            None => Ok(Vec::new())
+        }
+    }
+}
+
+impl Scope {
+    // Remove all the entries of a registration from this scope, the types left without entries fall back on the parent
+    fn remove_registration(&self, id: usize) {
+        self.registries.borrow_mut().retain(|_, registry_vec| {
+            registry_vec.remove_registration(id);
+            !registry_vec.is_empty()
+        });
+        self.scoped_instances.borrow_mut().remove(&id);
+        self.pending.borrow_mut().retain(|pending| pending.registration.id != id);
+    }
+
+    // Identifiers of the registrations providing a type in this scope only
+    fn local_registrations(&self, id: &TypeId) -> Vec<usize> {
+        self.registries.borrow().get(id).map_or(Vec::new(), |registry_vec| registry_vec.registrations().iter().map(|registration| registration.id).collect())
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        trace!("Drop registry memory");
+        self.scoped_instances.get_mut().clear();
+        self.decorators.get_mut().clear();
+        self.registries.get_mut().clear();
+    }
+}
 
 pub struct Registry {
-    registries: HashMap<TypeId, Box<dyn RegistryVec>>
+    scope: Rc<Scope>
 }
 
 impl Registry {
     pub fn new() -> Self {
         Self {
-            registries: HashMap::new()
+            scope: Rc::new(Scope::new(None))
         }
     }
 
-    pub fn register_instance<T>(&mut self, component: T) ->  Arc<Mutex<T>> where T: Component + 'static {
-        let component: Arc<Mutex<T>> = Arc::new(Mutex::new(component));
-        T::register(component.clone(), self);
-        component
+    /// Create a child registry which resolves its own registrations first and falls back on this registry.
+    /// Scoped components are built once in each child and everything registered in the child is dropped with it.
+    pub fn create_scope(&self) -> Registry {
+        Self {
+            scope: Rc::new(Scope::new(Some(self.scope.clone())))
+        }
     }
 
-    pub fn register_with_type<T>(&mut self, component: Arc<Mutex<T>>) where T: ?Sized + 'static {
-        let id = TypeId::of::<T>();
-        if let Some(component_vec) = self.registries.get_mut(&id) {
-            if let Some(registry_vec) = component_vec.as_any_mut().downcast_mut::<Mutex<Vec<Arc<Mutex<T>>>>>() {
-                registry_vec.lock().unwrap().push(component);
-            }
-            else {
-                println!("Error during conversion");
+    pub fn register_instance<T>(&mut self, component: T) -> Result<T::Handle> where T: Component + ComponentStorage + 'static {
+        self.register_component(component, Registration::default())
+    }
+
+    pub fn register_named_instance<T>(&mut self, name: &str, component: T) -> Result<T::Handle> where T: Component + ComponentStorage + 'static {
+        self.register_component(component, Registration::named(name))
+    }
+
+    pub fn register_with_type<H>(&mut self, entry: RegistryEntry<H>) -> Result<()> where H: Clone + 'static {
+        let id = TypeId::of::<H>();
+        // The decorators of the parents apply to the components registered in a scope, the scope ones are the outermost
+        let mut scopes = vec![&self.scope];
+        while let Some(parent) = &scopes[scopes.len() - 1].parent {
+            scopes.push(parent);
+        }
+        let decorators: Vec<Decorator<H>> = scopes.iter().rev()
+            .flat_map(|scope| scope.decorators.borrow().get(&id).and_then(|decorators| decorators.downcast_ref::<Vec<Decorator<H>>>()).cloned().unwrap_or_default())
+            .collect();
+        let entry = decorators.into_iter().fold(entry, |entry, decorator| entry.decorate(decorator));
+        let mut registries = self.scope.registries.borrow_mut();
+        if let Some(component_vec) = registries.get_mut(&id) {
+            match component_vec.as_any_mut().downcast_mut::<Vec<RegistryEntry<H>>>() {
+                Some(registry_vec) => registry_vec.push(entry),
+                None => return Err!(RegistryError::ComponentTypeError { name: type_name::<H>() })
             }
         }
         else {
-            let mut component_vec: Vec<Arc<Mutex<T>>> = Vec::new();
-            component_vec.push(component);
-            self.registries.insert(id, Box::new(Mutex::new(component_vec)));
+            let component_vec: Vec<RegistryEntry<H>> = vec![entry];
+            registries.insert(id, Box::new(component_vec));
         }
+        Ok(())
+    }
+
+    /// Register a component built with `new_from_reg`, a ConditionError is returned when its `when` condition does not hold
+    pub fn register<T>(&mut self) -> Result<T::Handle> where T: Component + ComponentStorage + Injection + 'static {
+        self.check_condition::<T>()?;
+        let component = self.construct(type_name::<T>(), T::new_from_reg)?;
+        self.register_component(component, Registration { dependencies: T::new_dependencies(), ..Registration::new() })
     }
 
+    /// Register a component built with its asynchronous constructor
+    pub async fn register_async<T>(&mut self) -> Result<T::Handle> where T: Component + ComponentStorage + Injection + 'static {
+        self.check_condition::<T>()?;
+        let component = self.construct_async::<T>().await?;
+        self.register_component(component, Registration { dependencies: T::new_dependencies(), ..Registration::new() })
+    }
 
-    pub fn register<T>(&mut self) -> Result<Arc<Mutex<T>>> where T: Component + Injection + 'static {
-        let component: Arc<Mutex<T>> = Arc::new(Mutex::new(T::new_from_reg(self)?));
-        T::register(component.clone(), self);
-        Ok(component)
+    pub fn register_named<T>(&mut self, name: &str) -> Result<T::Handle> where T: Component + ComponentStorage + Injection + 'static {
+        self.check_condition::<T>()?;
+        let component = self.construct(type_name::<T>(), T::new_from_reg)?;
+        self.register_component(component, Registration { dependencies: T::new_dependencies(), ..Registration::named(name) })
     }
 
-    pub fn get<T>(&mut self) -> Result<Arc<Mutex<T>>> where T: ?Sized + 'static {
-        let id = TypeId::of::<T>();
-        if let Some(registry_entry) = self.registries.get_mut(&id) {
-            if let Some(registry_vec) = registry_entry.as_any_mut().downcast_mut::<Mutex<Vec<Arc<Mutex<T>>>>>() {
-                let len = registry_vec.lock().unwrap().len();
-                match len {
-                    0 => {
-                        Err!(RegistryError::NoComponentError { name: type_name::<T>() })
-                    },
-                    1 => {
-                        Ok(registry_vec.lock().unwrap()[0].clone())
-                    },
-                    _ => {
-                        Err!(RegistryError::MultipleComponentsError { name: type_name::<T>() })
-                    }
+    /// Register a component built with another constructor than `new`, usually an `#[inject]` method with the
+    /// dependencies generated for it: `register_with::<T, _>(T::new_in_memory_from_reg, T::new_in_memory_dependencies())`
+    pub fn register_with<T, F>(&mut self, constructor: F, dependencies: Vec<Dependency>) -> Result<T::Handle> where T: Component + ComponentStorage + 'static, F: FnOnce(&mut Registry) -> Result<T> {
+        self.check_condition::<T>()?;
+        let component = self.construct(type_name::<T>(), constructor)?;
+        self.register_component(component, Registration { dependencies, ..Registration::new() })
+    }
+
+    /// Register a component built with its `#[inject(profile = "...")]` constructor of `profile`,
+    /// or with `new` when it has none
+    pub fn register_profile<T>(&mut self, profile: &str) -> Result<T::Handle> where T: Component + ComponentStorage + Injection + 'static {
+        self.check_condition::<T>()?;
+        let (constructor, dependencies): (Constructor<T>, _) = T::profile_constructor(profile)
+            .unwrap_or_else(|| (T::new_from_reg, T::new_dependencies()));
+        let component = self.construct(type_name::<T>(), constructor)?;
+        self.register_component(component, Registration { dependencies, ..Registration::new() })
+    }
+
+    /// Register a component only if `predicate` holds for the registered Config (and its `when` condition if any)
+    pub fn register_if<T, P>(&mut self, predicate: P) -> Result<Option<T::Handle>> where T: Component + ComponentStorage + Injection + 'static, P: FnOnce(&dyn Config) -> bool {
+        if !config::with_config(self, predicate).unwrap_or(false) || !self.condition_holds::<T>() {
+            return Ok(None);
+        }
+        self.register::<T>().map(Some)
+    }
+
+    /// Register a component built with `new_from_reg` each time it is resolved
+    pub fn register_transient<T>(&mut self) -> Result<()> where T: Component + ComponentStorage + Injection + 'static {
+        self.check_condition::<T>()?;
+        let factory: Factory<T::Handle> = Rc::new(|registry: &mut Registry| Ok(registry.construct(type_name::<T>(), T::new_from_reg)?.into_handle()));
+        self.register_binding::<T>(Binding::Factory(factory), Registration { dependencies: T::new_dependencies(), ..Registration::with_lifetime(Lifetime::Transient) })?;
+        Ok(())
+    }
+
+    /// Register a component built with `new_from_reg` once for each registry scope in which it is resolved
+    pub fn register_scoped<T>(&mut self) -> Result<()> where T: Component + ComponentStorage + Injection + 'static {
+        self.check_condition::<T>()?;
+        let registration = Registration { dependencies: T::new_dependencies(), ..Registration::with_lifetime(Lifetime::Scoped) };
+        let id = registration.id;
+        let factory: Factory<T::Handle> = Rc::new(move |registry: &mut Registry| registry.scoped_instance::<T>(id));
+        self.register_binding::<T>(Binding::Factory(factory), registration)?;
+        Ok(())
+    }
+
+    /// Declare a singleton component without building it: declared components can be registered in any order
+    /// and are built in the order of their dependencies by `build` (or on their first resolution).
+    /// A component whose `when` condition does not hold is not declared.
+    pub fn declare<T>(&mut self) -> Result<()> where T: Component + ComponentStorage + Injection + 'static {
+        if !self.condition_holds::<T>() {
+            return Ok(());
+        }
+        let (factory, built) = cached(type_name::<T>(), in_owner_scope(&self.scope, type_name::<T>(), |registry: &mut Registry| Ok(registry.construct(type_name::<T>(), T::new_from_reg)?.into_handle())));
+        let registration = self.register_binding::<T>(Binding::Singleton(factory.clone(), built), Registration { dependencies: T::new_dependencies(), ..Registration::new() })?;
+        self.push_pending(registration, factory);
+        Ok(())
+    }
+
+    /// Register for the trait `T` a singleton built by `factory` on its first resolution (or by `build`),
+    /// used for the types which cannot be annotated with #[injectable] (connection pools, HTTP clients...)
+    pub fn register_factory<T, F>(&mut self, factory: F) -> Result<()> where T: ?Sized + 'static, F: Fn(&mut Registry) -> Result<Arc<Mutex<T>>> + 'static {
+        let (factory, built) = cached(type_name::<T>(), in_owner_scope(&self.scope, type_name::<T>(), move |registry: &mut Registry| registry.construct(type_name::<T>(), &factory)));
+        let entry = RegistryEntry::new(Binding::Singleton(factory.clone(), built), Registration { component: type_name::<T>(), ..Registration::new() });
+        let registration = entry.registration.clone();
+        self.register_with_type::<Arc<Mutex<T>>>(entry)?;
+        self.push_pending(registration, factory);
+        Ok(())
+    }
+
+    // Add a singleton factory to the components constructed by `build`
+    fn push_pending<H>(&mut self, registration: Rc<Registration>, factory: Factory<H>) where H: 'static {
+        let build: Build = Rc::new(move |registry: &mut Registry| factory(registry).map(|_| ()));
+        self.scope.pending.borrow_mut().push(Pending { registration, build });
+    }
+
+    /// Remove a component registered as an instance, or a built singleton (`declare`, `register_factory`),
+    /// from its type and from all its traits.
+    /// The handle can be the one of the component or of one of its traits (`Arc<Mutex<dyn Trait>>`...).
+    pub fn unregister<T>(&mut self, handle: &Arc<T>) -> Result<()> where T: ?Sized + 'static {
+        let corresponds = |entry: &&RegistryEntry<Arc<T>>| match &entry.binding {
+            Binding::Instance(component) => Arc::ptr_eq(component, handle),
+            Binding::Singleton(_, built) => built().map_or(false, |component| Arc::ptr_eq(&component, handle)),
+            Binding::Factory(_) => false
+        };
+        let id = self.scope.registries.borrow().get(&TypeId::of::<Arc<T>>())
+            .and_then(|registry_vec| registry_vec.as_any().downcast_ref::<Vec<RegistryEntry<Arc<T>>>>())
+            .and_then(|registry_vec| registry_vec.iter().find(corresponds))
+            .map(|entry| entry.registration.id);
+        match id {
+            Some(id) => {
+                self.scope.remove_registration(id);
+                Ok(())
+            },
+            None => Err!(RegistryError::UnknownHandleError { name: type_name::<Arc<T>>() })
+        }
+    }
+
+    /// Replace all the components registered in this registry for the trait `T` by `component`,
+    /// whatever their storage. The components which already received the previous ones keep them.
+    pub fn replace<T, C>(&mut self, component: C) -> Result<C::Handle> where T: ?Sized + 'static, C: Component + ComponentStorage + 'static {
+        if !C::struct_impl_trait::<T>() && TypeId::of::<T>() != TypeId::of::<C>() {
+            return Err!(RegistryError::ComponentTypeError { name: type_name::<C>() });
+        }
+        let ids: Vec<usize> = [TypeId::of::<Arc<Mutex<T>>>(), TypeId::of::<Arc<RwLock<T>>>(), TypeId::of::<Arc<T>>()].iter()
+            .flat_map(|id| self.scope.local_registrations(id))
+            .collect();
+        for id in ids {
+            self.scope.remove_registration(id);
+        }
+        self.register_component(component, Registration::default())
+    }
+
+    /// Register `component` for the trait `T` in priority over the other registrations: while it is registered,
+    /// `get` and the injected parameters receive it and `get_all` returns only the overriding components.
+    /// The components resolved with a name (`get_named`, `#[named]`) are not overridden.
+    pub fn override_with<T, C>(&mut self, component: C) -> Result<C::Handle> where T: ?Sized + 'static, C: Component + ComponentStorage + 'static {
+        if !C::struct_impl_trait::<T>() && TypeId::of::<T>() != TypeId::of::<C>() {
+            return Err!(RegistryError::ComponentTypeError { name: type_name::<C>() });
+        }
+        let overrides = vec![TypeId::of::<Arc<Mutex<T>>>(), TypeId::of::<Arc<RwLock<T>>>(), TypeId::of::<Arc<T>>()];
+        self.register_component(component, Registration { overrides, ..Registration::new() })
+    }
+
+    /// Wrap the components registered for the trait `T` in this registry and in its scopes, before and after
+    /// the call, with `decorator` (timing, logging, retries...). The last decorator added is the outermost one.
+    pub fn decorate<T, F>(&mut self, decorator: F) where T: ?Sized + 'static, F: Fn(Arc<Mutex<T>>) -> Arc<Mutex<T>> + 'static {
+        self.decorate_handle::<Arc<Mutex<T>>, F>(decorator)
+    }
+
+    /// Wrap the components registered for a handle type (`Arc<RwLock<dyn Trait>>`, `Arc<dyn Trait>`...) with `decorator`
+    pub fn decorate_handle<H, F>(&mut self, decorator: F) where H: Clone + 'static, F: Fn(H) -> H + 'static {
+        let id = TypeId::of::<H>();
+        let decorator: Decorator<H> = Rc::new(decorator);
+        if let Some(registry_vec) = self.scope.registries.borrow_mut().get_mut(&id).and_then(|registry_vec| registry_vec.as_any_mut().downcast_mut::<Vec<RegistryEntry<H>>>()) {
+            for entry in registry_vec.iter_mut() {
+                *entry = entry.clone().decorate(decorator.clone());
+            }
+        }
+        let mut decorators = self.scope.decorators.borrow_mut();
+        if let Some(decorators) = decorators.entry(id).or_insert_with(|| Box::new(Vec::<Decorator<H>>::new())).downcast_mut::<Vec<Decorator<H>>>() {
+            decorators.push(decorator);
+        }
+    }
+
+    /// Build all the declared components in the order of their dependencies.
+    /// A missing dependency or a circular dependency is reported before any construction.
+    pub fn build(&mut self) -> Result<()> {
+        let declared: Vec<Rc<Registration>> = self.scope.pending.borrow().iter().map(|pending| pending.registration.clone()).collect();
+        let order = self.dependency_order(&declared)?;
+        let builds: HashMap<usize, Build> = self.scope.pending.borrow_mut().drain(..).map(|pending| (pending.registration.id, pending.build)).collect();
+        for registration in order {
+            if let Some(build) = builds.get(&registration.id) {
+                build(self)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Describe the components of this registry and of its parent scopes with the handle types
+    /// under which they are registered and the components providing their dependencies
+    pub fn describe(&self) -> RegistryDescription {
+        let mut components = Vec::new();
+        let mut scope = Some(&self.scope);
+        let mut depth = 0;
+        while let Some(current) = scope {
+            let mut level: BTreeMap<usize, ComponentDescription> = BTreeMap::new();
+            for registry_vec in current.registries.borrow().values() {
+                for registration in registry_vec.registrations() {
+                    level.entry(registration.id)
+                        .or_insert_with(|| Self::describe_registration(current, &registration, depth))
+                        .provides.push(registry_vec.handle_name());
                 }
             }
-            else {
-                Err!(RegistryError::ComponentTypeError)
+            for mut description in level.into_values() {
+                description.provides.sort_unstable();
+                components.push(description);
             }
+            scope = current.parent.as_ref();
+            depth += 1;
         }
-        else {
-            Err!(RegistryError::NoComponentError { name: type_name::<T>() })
+        // The components are numbered in the description, the registration ids depend on all the registries of the process
+        let indexes: HashMap<usize, usize> = components.iter().enumerate().map(|(index, component)| (component.id, index)).collect();
+        for (index, component) in components.iter_mut().enumerate() {
+            component.id = index;
+            for dependency in component.dependencies.iter_mut() {
+                dependency.providers = dependency.providers.iter().filter_map(|provider| indexes.get(provider).copied()).collect();
+            }
         }
+        RegistryDescription { components }
     }
-}
 
-impl Drop for Registry {
-    fn drop(&mut self) {
-        println!("Drop registry memory");
-        self.registries.clear();
+    // Dependencies are resolved in the scope of the registration, like during the construction
+    fn describe_registration(scope: &Scope, registration: &Registration, depth: usize) -> ComponentDescription {
+        let dependencies = registration.dependencies.iter().map(|dependency| DependencyDescription {
+            type_name: dependency.type_name,
+            name: dependency.name,
+            multiple: dependency.multiple,
+            deferred: dependency.deferred,
+            optional: dependency.optional,
+            providers: scope.registrations(&dependency.type_id, dependency.name).iter()
+                .map(|provider| provider.id)
+                .collect()
+        }).collect();
+        ComponentDescription {
+            id: registration.id,
+            component: registration.component,
+            name: registration.name.clone(),
+            lifetime: registration.lifetime,
+            scope: depth,
+            provides: Vec::new(),
+            dependencies
+        }
+    }
+
+    /// Return if a component is registered for the handle type `H` in this registry or in its parents
+    pub fn is_registered<H>(&self) -> bool where H: 'static {
+        !self.scope.registrations(&TypeId::of::<H>(), None).is_empty()
+    }
+
+    /// Get all the components registered for a type, each one after the components it depends on
+    pub fn get_all_by_dependencies<T>(&mut self) -> Result<Vec<Arc<Mutex<T>>>> where T: ?Sized + 'static {
+        let entries = self.entries::<Arc<Mutex<T>>>()?;
+        let registrations: Vec<Rc<Registration>> = entries.iter().map(|entry| entry.registration.clone()).collect();
+        let order = self.dependency_order(&registrations)?;
+        order.iter()
+            .filter_map(|registration| entries.iter().find(|entry| entry.registration.id == registration.id))
+            .map(|entry| entry.resolve(self))
+            .collect()
+    }
+
+    // Sort registrations and their transitive dependencies so that each one comes after its dependencies
+    fn dependency_order(&self, registrations: &[Rc<Registration>]) -> Result<Vec<Rc<Registration>>> {
+        let mut order = Vec::new();
+        let mut done = HashSet::new();
+        let mut path = Vec::new();
+        for registration in registrations.iter() {
+            self.visit_dependencies(registration, &mut done, &mut path, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    // Depth first search, a registration is added to the order once all its dependencies are
+    fn visit_dependencies(&self, registration: &Rc<Registration>, done: &mut HashSet<usize>, path: &mut Vec<Rc<Registration>>, order: &mut Vec<Rc<Registration>>) -> Result<()> {
+        if done.contains(&registration.id) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|visited| visited.id == registration.id) {
+            let path = path[start..].iter().chain(std::iter::once(registration)).map(|visited| visited.component).collect::<Vec<&str>>().join(" -> ");
+            return Err!(RegistryError::CircularDependencyError { path });
+        }
+        path.push(registration.clone());
+        for dependency in registration.dependencies.iter().filter(|dependency| !dependency.deferred) {
+            let providers = self.scope.registrations(&dependency.type_id, dependency.name);
+            if providers.is_empty() && !dependency.multiple && !dependency.optional {
+                return Err!(RegistryError::MissingDependencyError { component: registration.component, dependency: dependency.to_string() });
+            }
+            for provider in providers.iter() {
+                self.visit_dependencies(provider, done, path, order)?;
+            }
+        }
+        path.pop();
+        done.insert(registration.id);
+        order.push(registration.clone());
+        Ok(())
+    }
+
+    // Return if the `when` condition of a component holds against the registered Config
+    fn condition_holds<T>(&mut self) -> bool where T: ComponentStorage {
+        T::CONDITION.map_or(true, |condition| config::condition_holds(self, condition))
+    }
+
+    fn check_condition<T>(&mut self) -> Result<()> where T: ComponentStorage {
+        match T::CONDITION {
+            Some(condition) if !self.condition_holds::<T>() => Err!(RegistryError::ConditionError { name: type_name::<T>(), condition }),
+            _ => Ok(())
+        }
+    }
+
+    fn register_component<T>(&mut self, component: T, registration: Registration) -> Result<T::Handle> where T: Component + ComponentStorage + 'static {
+        let component = component.into_handle();
+        self.register_binding::<T>(Binding::Instance(component.clone()), registration)?;
+        Ok(component)
+    }
+
+    fn register_binding<T>(&mut self, binding: Binding<T::Handle>, registration: Registration) -> Result<Rc<Registration>> where T: Component + ComponentStorage + 'static {
+        let entry = RegistryEntry::new(binding, Registration { component: type_name::<T>(), primary: T::PRIMARY, priority: T::PRIORITY, ..registration });
+        let registration = entry.registration.clone();
+        T::register(entry, self)?;
+        Ok(registration)
+    }
+
+    // Call the constructor of a component, its errors report the chain of the components under construction.
+    // A component already in the chain depends on itself (transient and scoped components are not memoized).
+    fn construct<R, F>(&mut self, component: &'static str, constructor: F) -> Result<R> where F: FnOnce(&mut Registry) -> Result<R> {
+        let constructing = Constructing::start(&self.scope, component)?;
+        let result = constructor(self);
+        result.map_err(|error| constructor_error(error, component, constructing.chain()))
+    }
+
+    // Call the asynchronous constructor of a component like `construct`, the component leaves the chain
+    // even when the future is dropped before its end
+    async fn construct_async<T>(&mut self) -> Result<T> where T: Injection + 'static {
+        let constructing = Constructing::start(&self.scope, type_name::<T>())?;
+        let result = T::new_from_reg_async(self).await;
+        result.map_err(|error| constructor_error(error, type_name::<T>(), constructing.chain()))
+    }
+
+    // Return the instance of a scoped registration for this registry, building it on first use
+    fn scoped_instance<T>(&mut self, id: usize) -> Result<T::Handle> where T: Component + ComponentStorage + Injection + 'static {
+        self.scoped_value(id, |registry: &mut Registry| Ok(registry.construct(type_name::<T>(), T::new_from_reg)?.into_handle()))
+    }
+
+    // Return the value identified by `id` in this registry, building it on first use
+    fn scoped_value<H, F>(&mut self, id: usize, build: F) -> Result<H> where H: Clone + 'static, F: FnOnce(&mut Registry) -> Result<H> {
+        if let Some(component) = self.scope.scoped_instances.borrow().get(&id).and_then(|component| component.downcast_ref::<H>()) {
+            return Ok(component.clone());
+        }
+        let component = build(self)?;
+        self.scope.scoped_instances.borrow_mut().insert(id, Box::new(component.clone()));
+        Ok(component)
+    }
+
+    fn entries<H>(&self) -> Result<Vec<RegistryEntry<H>>> where H: Clone + 'static {
+        self.scope.entries::<H>(None)
+    }
+
+    // Select the entry to resolve: the only one, or the one with the highest rank if it is unique
+    fn preferred<H>(entries: &[RegistryEntry<H>]) -> Option<&RegistryEntry<H>> where H: Clone + 'static {
+        let best = entries.iter().map(|entry| entry.rank()).max()?;
+        let mut candidates = entries.iter().filter(|entry| entry.rank() == best);
+        match (candidates.next(), candidates.next()) {
+            (Some(entry), None) => Some(entry),
+            _ => None
+        }
+    }
+
+    // Resolve the entry registered for a handle type, `name` is the type reported in the errors
+    fn resolve_one<H>(&mut self, name: &'static str) -> Result<H> where H: Clone + 'static {
+        let entries = self.entries::<H>()?;
+        if entries.is_empty() {
+            return Err!(RegistryError::NoComponentError { name });
+        }
+        match Self::preferred(&entries) {
+            Some(entry) => entry.resolve(self),
+            None => Err!(RegistryError::MultipleComponentsError { name })
+        }
+    }
+
+    // Resolve the only entry registered with the name `qualifier` for a handle type
+    fn resolve_named<H>(&mut self, name: &'static str, qualifier: &str) -> Result<H> where H: Clone + 'static {
+        let entries = self.scope.entries::<H>(Some(qualifier))?;
+        if entries.is_empty() {
+            return Err!(RegistryError::NoNamedComponentError { name, qualifier: qualifier.to_string() });
+        }
+        match Self::preferred(&entries) {
+            Some(entry) => entry.resolve(self),
+            None => Err!(RegistryError::MultipleNamedComponentsError { name, qualifier: qualifier.to_string() })
+        }
+    }
+
+    /// Get a component stored with the default storage
+    pub fn get<T>(&mut self) -> Result<Arc<Mutex<T>>> where T: ?Sized + 'static {
+        self.resolve_one::<Arc<Mutex<T>>>(type_name::<T>())
+    }
+
+    /// Get a component stored with `storage = rwlock`
+    pub fn get_rw<T>(&mut self) -> Result<Arc<RwLock<T>>> where T: ?Sized + 'static {
+        self.resolve_one::<Arc<RwLock<T>>>(type_name::<T>())
+    }
+
+    /// Get a component stored with `storage = shared`
+    pub fn get_shared<T>(&mut self) -> Result<Arc<T>> where T: ?Sized + 'static {
+        self.resolve_one::<Arc<T>>(type_name::<T>())
+    }
+
+    /// Get a component by the type of its handle (`Arc<Mutex<T>>`, `Arc<RwLock<T>>` or `Arc<T>`)
+    pub fn get_handle<H>(&mut self) -> Result<H> where H: Clone + 'static {
+        self.resolve_one::<H>(type_name::<H>())
+    }
+
+    /// Get a component stored with the default storage, None if no component is registered
+    pub fn get_optional<T>(&mut self) -> Result<Option<Arc<Mutex<T>>>> where T: ?Sized + 'static {
+        self.get_optional_handle::<Arc<Mutex<T>>>()
+    }
+
+    /// Get a component by the type of its handle, None if no component is registered
+    pub fn get_optional_handle<H>(&mut self) -> Result<Option<H>> where H: Clone + 'static {
+        if self.entries::<H>()?.is_empty() {
+            return Ok(None);
+        }
+        self.resolve_one::<H>(type_name::<H>()).map(Some)
+    }
+
+    /// Get all the components registered for a type, in registration order
+    pub fn get_all<T>(&mut self) -> Result<Vec<Arc<Mutex<T>>>> where T: ?Sized + 'static {
+        self.get_all_handles::<Arc<Mutex<T>>>()
+    }
+
+    /// Get all the components registered for a handle type, in registration order
+    pub fn get_all_handles<H>(&mut self) -> Result<Vec<H>> where H: Clone + 'static {
+        self.entries::<H>()?.iter().map(|entry| entry.resolve(self)).collect()
+    }
+
+    /// Return a reference resolving the component on its first use
+    pub fn lazy<T>(&self) -> Lazy<T> where T: ?Sized + 'static {
+        Lazy { scope: Rc::downgrade(&self.scope), name: None, component: RefCell::new(None) }
+    }
+
+    /// Return a reference resolving the component registered with the name `name` on its first use
+    pub fn lazy_named<T>(&self, name: &str) -> Lazy<T> where T: ?Sized + 'static {
+        Lazy { scope: Rc::downgrade(&self.scope), name: Some(name.to_string()), component: RefCell::new(None) }
+    }
+
+    /// Return a factory resolving the component on each call
+    pub fn provider<T>(&self) -> Provider<T> where T: ?Sized + 'static {
+        Provider { scope: Rc::downgrade(&self.scope), name: None, component: std::marker::PhantomData }
+    }
+
+    /// Return a factory resolving the component registered with the name `name` on each call
+    pub fn provider_named<T>(&self, name: &str) -> Provider<T> where T: ?Sized + 'static {
+        Provider { scope: Rc::downgrade(&self.scope), name: Some(name.to_string()), component: std::marker::PhantomData }
+    }
+
+    /// Get the component registered with the name `name`
+    pub fn get_named<T>(&mut self, name: &str) -> Result<Arc<Mutex<T>>> where T: ?Sized + 'static {
+        self.resolve_named::<Arc<Mutex<T>>>(type_name::<T>(), name)
+    }
+
+    /// Get the component registered with the name `name` by the type of its handle
+    pub fn get_named_handle<H>(&mut self, name: &str) -> Result<H> where H: Clone + 'static {
+        self.resolve_named::<H>(type_name::<H>(), name)
+    }
+
+    /// Get the component registered with the name `name` by the type of its handle, None if there is none
+    pub fn get_optional_named_handle<H>(&mut self, name: &str) -> Result<Option<H>> where H: Clone + 'static {
+        if self.scope.entries::<H>(Some(name))?.is_empty() {
+            return Ok(None);
+        }
+        self.resolve_named::<H>(type_name::<H>(), name).map(Some)
     }
 }
@@ -1,4 +1,5 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 pub use injection_macros::*;
 pub use tools_macros::*;
@@ -6,40 +7,263 @@ pub use tools_macros::*;
 pub mod share;
 pub mod error;
 pub mod injection;
+pub mod description;
 pub mod constant;
 pub mod service;
+pub mod lifecycle;
+pub mod shutdown;
 pub mod config;
 pub mod logger;
 pub mod pubsub;
 pub mod service_discovery;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 pub struct Microservice {
-    pub registry: injection::Registry
+    pub registry: injection::Registry,
+    started: Vec<Arc<Mutex<dyn lifecycle::Lifecycle>>>,
+    registered: Vec<Arc<Mutex<dyn service_discovery::ServiceDiscovery>>>,
+    shutdown: shutdown::Shutdown,
+    shutdown_timeout: Duration
 }
 
 impl Microservice {
     pub fn new() -> Microservice {
         Microservice {
-            registry: injection::Registry::new()
+            registry: injection::Registry::new(),
+            started: Vec::new(),
+            registered: Vec::new(),
+            shutdown: shutdown::Shutdown::new(),
+            shutdown_timeout: Duration::from_secs(30)
         }
     }
 
-    pub fn register_instance<T>(&mut self, component: T) -> Arc<Mutex<T>> where T: injection::Component + 'static {
+    /// Create a child registry resolving its own registrations first and falling back on the microservice registry
+    pub fn create_scope(&self) -> injection::Registry {
+        self.registry.create_scope()
+    }
+
+    pub fn register_instance<T>(&mut self, component: T) -> error::Result<T::Handle> where T: injection::Component + injection::ComponentStorage + 'static {
         self.registry.register_instance::<T>(component)
     }
 
-    pub fn register<T>(&mut self) -> error::Result<Arc<Mutex<T>>> where T: injection::Component + injection::Injection + 'static {
+    pub fn register<T>(&mut self) -> error::Result<T::Handle> where T: injection::Component + injection::ComponentStorage + injection::Injection + 'static {
         let result = self.registry.register::<T>()?;
-        if T::struct_impl_trait::<dyn logger::Logger>() {
-            let int_logger = self.get::<dyn logger::Logger>().unwrap();
-            logger::register_logger(int_logger.clone());
+        self.register_logger::<T>(None);
+        Ok(result)
+    }
+
+    pub async fn register_async<T>(&mut self) -> error::Result<T::Handle> where T: injection::Component + injection::ComponentStorage + injection::Injection + 'static {
+        let result = self.registry.register_async::<T>().await?;
+        self.register_logger::<T>(None);
+        Ok(result)
+    }
+
+    pub fn register_named<T>(&mut self, name: &str) -> error::Result<T::Handle> where T: injection::Component + injection::ComponentStorage + injection::Injection + 'static {
+        let result = self.registry.register_named::<T>(name)?;
+        self.register_logger::<T>(Some(name));
+        Ok(result)
+    }
+
+    /// Register a component built with another constructor than `new` and its dependencies:
+    /// `register_with::<T, _>(T::new_in_memory_from_reg, T::new_in_memory_dependencies())`
+    pub fn register_with<T, F>(&mut self, constructor: F, dependencies: Vec<injection::Dependency>) -> error::Result<T::Handle> where T: injection::Component + injection::ComponentStorage + 'static, F: FnOnce(&mut injection::Registry) -> error::Result<T> {
+        let result = self.registry.register_with::<T, F>(constructor, dependencies)?;
+        self.register_logger::<T>(None);
+        Ok(result)
+    }
+
+    /// Register a component built with its constructor of `profile`, or with `new` when it has none
+    pub fn register_profile<T>(&mut self, profile: &str) -> error::Result<T::Handle> where T: injection::Component + injection::ComponentStorage + injection::Injection + 'static {
+        let result = self.registry.register_profile::<T>(profile)?;
+        self.register_logger::<T>(None);
+        Ok(result)
+    }
+
+    /// Register a component only if `predicate` holds for the registered Config
+    pub fn register_if<T, P>(&mut self, predicate: P) -> error::Result<Option<T::Handle>> where T: injection::Component + injection::ComponentStorage + injection::Injection + 'static, P: FnOnce(&dyn config::Config) -> bool {
+        let result = self.registry.register_if::<T, P>(predicate)?;
+        if result.is_some() {
+            self.register_logger::<T>(None);
         }
         Ok(result)
     }
 
+    pub fn register_transient<T>(&mut self) -> error::Result<()> where T: injection::Component + injection::ComponentStorage + injection::Injection + 'static {
+        self.registry.register_transient::<T>()
+    }
+
+    pub fn register_scoped<T>(&mut self) -> error::Result<()> where T: injection::Component + injection::ComponentStorage + injection::Injection + 'static {
+        self.registry.register_scoped::<T>()
+    }
+
+    /// Remove a component registered as an instance, or a built singleton, from its type and from all its traits
+    pub fn unregister<T>(&mut self, handle: &Arc<T>) -> error::Result<()> where T: ?Sized + 'static {
+        self.registry.unregister::<T>(handle)
+    }
+
+    /// Replace all the components registered for the trait `T` by `component`
+    pub fn replace<T, C>(&mut self, component: C) -> error::Result<C::Handle> where T: ?Sized + 'static, C: injection::Component + injection::ComponentStorage + 'static {
+        self.registry.replace::<T, C>(component)
+    }
+
+    /// Register `component` for the trait `T` in priority over the other registrations
+    pub fn override_with<T, C>(&mut self, component: C) -> error::Result<C::Handle> where T: ?Sized + 'static, C: injection::Component + injection::ComponentStorage + 'static {
+        self.registry.override_with::<T, C>(component)
+    }
+
+    /// Wrap the components registered for the trait `T` with `decorator`
+    pub fn decorate<T, F>(&mut self, decorator: F) where T: ?Sized + 'static, F: Fn(Arc<Mutex<T>>) -> Arc<Mutex<T>> + 'static {
+        self.registry.decorate::<T, F>(decorator)
+    }
+
+    /// Declare a component which will be built by `build` once all the components are declared
+    pub fn declare<T>(&mut self) -> error::Result<()> where T: injection::Component + injection::ComponentStorage + injection::Injection + 'static {
+        self.registry.declare::<T>()
+    }
+
+    /// Register for the trait `T` a singleton built by `factory` on its first resolution (or by `build`)
+    pub fn register_factory<T, F>(&mut self, factory: F) -> error::Result<()> where T: ?Sized + 'static, F: Fn(&mut injection::Registry) -> error::Result<Arc<Mutex<T>>> + 'static {
+        self.registry.register_factory::<T, F>(factory)
+    }
+
+    /// Build the declared components in the order of their dependencies
+    pub fn build(&mut self) -> error::Result<()> {
+        self.registry.build()?;
+        if let Ok(int_logger) = self.get::<dyn logger::Logger>() {
+            logger::register_logger(int_logger);
+        }
+        Ok(())
+    }
+
+    /// Start the Lifecycle components, each one after its dependencies, then register the microservice
+    /// in the service discoveries. If a component fails to start, the started ones are stopped.
+    // The components stay locked while they start or stop
+    #[allow(clippy::await_holding_lock)]
+    pub async fn start(&mut self) -> error::Result<()> {
+        self.check_default_storage::<dyn lifecycle::Lifecycle>()?;
+        self.check_default_storage::<dyn service_discovery::ServiceDiscovery>()?;
+        self.check_default_storage::<dyn logger::Logger>()?;
+        for component in self.registry.get_all_by_dependencies::<dyn lifecycle::Lifecycle>()? {
+            if let Err(e) = component.lock().unwrap().start().await {
+                error!("Unable to start component: {}", e);
+                // Stop errors are logged, the start error is returned
+                let _ = self.stop().await;
+                return Err(e);
+            }
+            self.started.push(component);
+        }
+        for discovery in self.registry.get_all::<dyn service_discovery::ServiceDiscovery>()? {
+            if let Err(e) = discovery.lock().unwrap().register().await {
+                error!("Unable to register service: {}", e);
+                let _ = self.stop().await;
+                return Err(e);
+            }
+            self.registered.push(discovery);
+        }
+        Ok(())
+    }
+
+    // The components of a trait used by the microservice must not be registered with another storage than
+    // Arc<Mutex<_>>, they would be ignored (a trait extending Lifecycle registers them as Arc<RwLock<dyn Lifecycle>>...)
+    fn check_default_storage<T>(&self) -> error::Result<()> where T: ?Sized + 'static {
+        if self.registry.is_registered::<Arc<RwLock<T>>>() || self.registry.is_registered::<Arc<T>>() {
+            return Err!(injection::RegistryError::StorageError { name: std::any::type_name::<T>() });
+        }
+        Ok(())
+    }
+
+    // Use as logger of the microservice a component just registered (with `name`) when it implements Logger
+    fn register_logger<T>(&mut self, name: Option<&str>) where T: injection::Component + 'static {
+        if T::struct_impl_trait::<dyn logger::Logger>() {
+            let int_logger = match name {
+                Some(name) => self.registry.get_named::<dyn logger::Logger>(name),
+                None => self.get::<dyn logger::Logger>()
+            };
+            if let Ok(int_logger) = int_logger {
+                logger::register_logger(int_logger);
+            }
+        }
+    }
+
+    /// Deregister the microservice from the service discoveries and stop the started Lifecycle components
+    /// in the reverse order. All the components are stopped even if one fails and the last error is returned.
+    #[allow(clippy::await_holding_lock)]
+    pub async fn stop(&mut self) -> error::Result<()> {
+        let mut result = Ok(());
+        while let Some(discovery) = self.registered.pop() {
+            if let Err(e) = discovery.lock().unwrap().deregister().await {
+                error!("Unable to deregister service: {}", e);
+                result = Err(e);
+            }
+        }
+        while let Some(component) = self.started.pop() {
+            if let Err(e) = component.lock().unwrap().stop().await {
+                error!("Unable to stop component: {}", e);
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Start the microservice, wait for a SIGINT/SIGTERM signal or a call to ShutdownHandle::shutdown,
+    /// then stop the microservice. The stop fails if it lasts more than the shutdown timeout.
+    pub async fn run(&mut self) -> error::Result<()> {
+        self.start().await?;
+        info!("Microservice started");
+        self.shutdown.wait().await?;
+        info!("Microservice stopping");
+        let timeout = self.shutdown_timeout;
+        match async_std::future::timeout(timeout, self.stop()).await {
+            Ok(result) => {
+                info!("Microservice stopped");
+                result
+            },
+            Err(_) => {
+                error!("Microservice not stopped after {:?}", timeout);
+                Err(shutdown::ShutdownError::Timeout { timeout }.into())
+            }
+        }
+    }
+
+    /// Return a handle to stop the microservice started with run
+    pub fn shutdown_handle(&self) -> shutdown::ShutdownHandle {
+        self.shutdown.handle()
+    }
+
+    /// Set the grace timeout given to the components to stop, 30 seconds by default
+    pub fn set_shutdown_timeout(&mut self, timeout: Duration) {
+        self.shutdown_timeout = timeout;
+    }
+
+    /// Describe the components registered in the microservice and their dependencies
+    pub fn describe(&self) -> description::RegistryDescription {
+        self.registry.describe()
+    }
+
     pub fn get<T>(&mut self) -> error::Result<Arc<Mutex<T>>> where T: ?Sized + 'static {
         self.registry.get::<T>()
     }
+
+    pub fn get_rw<T>(&mut self) -> error::Result<Arc<RwLock<T>>> where T: ?Sized + 'static {
+        self.registry.get_rw::<T>()
+    }
+
+    pub fn get_shared<T>(&mut self) -> error::Result<Arc<T>> where T: ?Sized + 'static {
+        self.registry.get_shared::<T>()
+    }
+
+    pub fn get_handle<H>(&mut self) -> error::Result<H> where H: Clone + 'static {
+        self.registry.get_handle::<H>()
+    }
+
+    pub fn get_all<T>(&mut self) -> error::Result<Vec<Arc<Mutex<T>>>> where T: ?Sized + 'static {
+        self.registry.get_all::<T>()
+    }
+
+    pub fn get_named<T>(&mut self, name: &str) -> error::Result<Arc<Mutex<T>>> where T: ?Sized + 'static {
+        self.registry.get_named::<T>(name)
+    }
 }
 
 impl Drop for Microservice {
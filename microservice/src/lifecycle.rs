@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+
+use crate::error::*;
+use crate::injection::Component;
+use crate::injectable;
+
+/// Optional hooks of a component, called by Microservice::start in the order of the dependencies
+/// and by Microservice::stop in the reverse order. The hooks are not required to be Send, a component
+/// holding a `Lazy` or a `Provider` (bound to the registry thread) can implement them:
+/// use `#[async_trait(?Send)]` on the implementation.
+#[async_trait(?Send)]
+#[injectable(Component)]
+pub trait Lifecycle: Component {
+    async fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use async_std::channel::{bounded, Receiver, Sender};
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+use crate::error::*;
+
+#[derive(Error, Debug)]
+pub enum ShutdownError {
+    #[error("Shutdown - Components not stopped after the grace timeout of {timeout:?}")]
+    Timeout { timeout: Duration },
+
+    #[error("Shutdown - Shutdown channel closed")]
+    ChannelClosed
+}
+
+/// Handle used to request the shutdown of a running microservice, it can be cloned and sent to other tasks
+#[derive(Clone, Debug)]
+pub struct ShutdownHandle {
+    sender: Sender<()>
+}
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        // A full channel means a shutdown is already requested
+        let _ = self.sender.try_send(());
+    }
+}
+
+/// Channel receiving the shutdown requests of a microservice
+pub(crate) struct Shutdown {
+    sender: Sender<()>,
+    receiver: Receiver<()>
+}
+
+impl Shutdown {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = bounded(1);
+        Self {
+            sender,
+            receiver
+        }
+    }
+
+    pub(crate) fn handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            sender: self.sender.clone()
+        }
+    }
+
+    /// Wait for a shutdown request or a SIGINT/SIGTERM signal
+    pub(crate) async fn wait(&self) -> Result<()> {
+        listen_signals(self.handle());
+        self.receiver.recv().await.map_err(|_| ShutdownError::ChannelClosed.into())
+    }
+}
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref SIGNAL_HANDLES: Mutex<Vec<ShutdownHandle>> = Mutex::new(Vec::new());
+}
+
+static SIGNAL_HANDLER: Once = Once::new();
+
+// Forward the termination signals to the handle, the process handler is installed only once
+fn listen_signals(handle: ShutdownHandle) {
+    SIGNAL_HANDLER.call_once(|| {
+        let result = ctrlc::set_handler(|| {
+            crate::info!("Termination signal received");
+            for handle in SIGNAL_HANDLES.lock().unwrap().iter() {
+                handle.shutdown();
+            }
+        });
+        if let Err(e) = result {
+            crate::warn!("Unable to listen the termination signals: {}", e);
+        }
+    });
+    let mut handles = SIGNAL_HANDLES.lock().unwrap();
+    handles.retain(|handle| !handle.sender.is_closed());
+    handles.push(handle);
+}
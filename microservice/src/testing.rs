@@ -0,0 +1,34 @@
+use crate::error::*;
+use crate::injection::{Component, ComponentStorage, Registry};
+use crate::Microservice;
+
+// Registration of a mock in the registry of the built microservice
+type Override = Box<dyn FnOnce(&mut Registry) -> Result<()>>;
+
+/// Mocks overriding the production components of a microservice built for the tests
+#[derive(Default)]
+pub struct Overrides {
+    overrides: Vec<Override>
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mock received in place of the components registered for the trait `T`
+    pub fn with<T, C>(mut self, mock: C) -> Self where T: ?Sized + 'static, C: Component + ComponentStorage + 'static {
+        self.overrides.push(Box::new(move |registry: &mut Registry| registry.override_with::<T, C>(mock).map(|_| ())));
+        self
+    }
+
+    /// Create a microservice with the mocks registered, the production components are then registered as usual
+    /// and the components depending on an overridden trait receive the mock
+    pub fn build(self) -> Result<Microservice> {
+        let mut microservice = Microservice::new();
+        for register in self.overrides {
+            register(&mut microservice.registry)?;
+        }
+        Ok(microservice)
+    }
+}
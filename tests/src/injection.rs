@@ -1,9 +1,12 @@
 #[cfg(test)]
 mod injection_tests {
-    use std::sync::{Arc, Mutex};
+    use std::sync::{Arc, Mutex, RwLock};
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use futures::FutureExt;
     use microservice::error::*;
     use microservice::constant::Constant;
     use microservice::service::Service;
+    use microservice::config::{Config, Value};
     use microservice::service_discovery::ServiceDiscovery;
     use microservice::injection::*;
     use microservice::*;
@@ -76,6 +79,110 @@ mod injection_tests {
         }
     }
 
+    #[injectable(Component)]
+    struct TestNamedConsumer {
+        pub first: Arc<Mutex<dyn Trait>>,
+        pub second: Arc<Mutex<dyn Trait>>
+    }
+
+    #[injector]
+    impl TestNamedConsumer {
+        #[inject]
+        fn new(#[named("first")] first: Arc<Mutex<dyn Trait>>, #[named("second")] second: Arc<Mutex<dyn Trait>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {
+                first,
+                second
+            })
+        }
+    }
+
+    #[injectable(Component)]
+    struct TestAllConsumer {
+        pub all: Vec<Arc<Mutex<dyn Trait>>>
+    }
+
+    #[injector]
+    impl TestAllConsumer {
+        #[inject]
+        fn new(all: Vec<Arc<Mutex<dyn Trait>>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {
+                all
+            })
+        }
+    }
+
+    #[injectable(Component)]
+    struct TestCycleA {}
+
+    #[injector]
+    impl TestCycleA {
+        #[inject]
+        fn new(_b: Arc<Mutex<TestCycleB>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {})
+        }
+    }
+
+    #[injectable(Component)]
+    struct TestCycleB {}
+
+    #[injector]
+    impl TestCycleB {
+        #[inject]
+        fn new(_a: Arc<Mutex<TestCycleA>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {})
+        }
+    }
+
+    #[derive(Debug)]
+    #[injectable(Component)]
+    struct TestAsyncComponent {
+        pub value: u16
+    }
+
+    #[injector]
+    impl TestAsyncComponent {
+        #[inject]
+        async fn new(component: Arc<Mutex<TestComponent>>) -> Result<Self> where Self: Sized + 'static {
+            async_std::task::yield_now().await;
+            let value = component.lock().unwrap().value + 1;
+            Ok(Self {
+                value
+            })
+        }
+    }
+
+    #[injectable(Component)]
+    struct TestLazyA {
+        pub b: Lazy<TestLazyB>,
+        pub transient: Provider<dyn Trait>
+    }
+
+    #[injector]
+    impl TestLazyA {
+        #[inject]
+        fn new(b: Lazy<TestLazyB>, transient: Provider<dyn Trait>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {
+                b,
+                transient
+            })
+        }
+    }
+
+    #[injectable(Component)]
+    struct TestLazyB {
+        pub a: Arc<Mutex<TestLazyA>>
+    }
+
+    #[injector]
+    impl TestLazyB {
+        #[inject]
+        fn new(a: Arc<Mutex<TestLazyA>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {
+                a
+            })
+        }
+    }
+
     #[injectable(Service)]
     trait TestTraitService: Service {}
 
@@ -85,6 +192,274 @@ mod injection_tests {
     impl TestTraitService for TestService {}
     impl Service for TestService {}
 
+    #[injectable(Trait, storage = rwlock)]
+    struct TestRwComponent {
+        pub value: u16
+    }
+
+    impl Trait for TestRwComponent {
+        fn get_value(&self) -> u16 {
+            self.value
+        }
+    }
+
+    #[injectable(Trait, storage = shared)]
+    struct TestSharedComponent {
+        pub value: AtomicU16
+    }
+
+    impl Trait for TestSharedComponent {
+        fn get_value(&self) -> u16 {
+            self.value.load(Ordering::Relaxed)
+        }
+    }
+
+    #[injectable(Trait, primary)]
+    struct TestPrimaryComponent {}
+
+    impl Trait for TestPrimaryComponent {
+        fn get_value(&self) -> u16 {
+            1
+        }
+    }
+
+    #[injectable(Trait, priority = 10)]
+    struct TestPriorityComponent {}
+
+    impl Trait for TestPriorityComponent {
+        fn get_value(&self) -> u16 {
+            2
+        }
+    }
+
+    #[injectable(Config)]
+    struct TestConfig {
+        pub backend: String
+    }
+
+    impl Config for TestConfig {
+        fn get(&self, field: &str) -> Result<Value> {
+            match field {
+                "backend" => Ok(Value::String(self.backend.clone())),
+                "port" => Ok(Value::Number(8080.into())),
+                _ => Err(anyhow::anyhow!("Field not found: {}", field))
+            }
+        }
+    }
+    impl Service for TestConfig {}
+
+    #[injectable(Trait, when = "backend == 'memory'")]
+    struct TestMemoryBackend {}
+
+    #[injector]
+    impl TestMemoryBackend {
+        #[inject]
+        fn new() -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {})
+        }
+    }
+
+    impl Trait for TestMemoryBackend {
+        fn get_value(&self) -> u16 {
+            1
+        }
+    }
+
+    #[derive(Debug)]
+    #[injectable(Trait, when = "backend == 'remote'")]
+    struct TestRemoteBackend {}
+
+    #[injector]
+    impl TestRemoteBackend {
+        #[inject]
+        fn new() -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {})
+        }
+    }
+
+    impl Trait for TestRemoteBackend {
+        fn get_value(&self) -> u16 {
+            2
+        }
+    }
+
+    #[derive(Debug)]
+    #[injectable(Component)]
+    struct TestConfigConsumer {
+        pub backend: String,
+        pub port: u16
+    }
+
+    #[injector]
+    impl TestConfigConsumer {
+        #[inject]
+        fn new(#[config(section = "backend")] backend: String, #[config("port")] port: u16) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self { backend, port })
+        }
+    }
+
+    #[derive(Debug)]
+    #[injectable(Component)]
+    struct TestMissingConfigConsumer {}
+
+    #[injector]
+    impl TestMissingConfigConsumer {
+        #[inject]
+        fn new(#[config("timeout")] _timeout: u16) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {})
+        }
+    }
+
+    #[injectable(Component)]
+    struct TestOptionalConsumer {
+        pub component: Option<Arc<Mutex<dyn Trait>>>,
+        pub unknown: Option<Arc<Mutex<dyn UnknownTrait>>>
+    }
+
+    #[injector]
+    impl TestOptionalConsumer {
+        #[inject]
+        fn new(component: Option<Arc<Mutex<dyn Trait>>>, unknown: Option<Arc<Mutex<dyn UnknownTrait>>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self { component, unknown })
+        }
+    }
+
+    #[injectable(Component)]
+    #[derive(Inject)]
+    struct TestDerivedConsumer {
+        #[named("first")]
+        pub first: Arc<Mutex<dyn Trait>>,
+        pub all: Vec<Arc<Mutex<dyn Trait>>>,
+        pub unknown: Option<Arc<Mutex<dyn UnknownTrait>>>,
+        #[config("port")]
+        pub port: u16,
+        #[default]
+        pub calls: u16,
+        #[default(String::from("derived"))]
+        pub label: String
+    }
+
+    #[injectable(Component)]
+    trait TestRepository<T>: Component {
+        fn find(&self, id: u16) -> Option<T>;
+    }
+
+    #[injectable(TestRepository<u16>, TestRepository<String>)]
+    struct TestMemoryRepository {}
+
+    impl TestRepository<u16> for TestMemoryRepository {
+        fn find(&self, id: u16) -> Option<u16> {
+            Some(id)
+        }
+    }
+
+    impl TestRepository<String> for TestMemoryRepository {
+        fn find(&self, id: u16) -> Option<String> {
+            Some(id.to_string())
+        }
+    }
+
+    #[injectable(Trait)]
+    enum TestEnumComponent {
+        Fixed,
+        Value(u16)
+    }
+
+    impl Trait for TestEnumComponent {
+        fn get_value(&self) -> u16 {
+            match self {
+                TestEnumComponent::Fixed => 1,
+                TestEnumComponent::Value(value) => *value
+            }
+        }
+    }
+
+    // Re-export of microservice by a facade crate
+    mod facade {
+        pub use microservice as core;
+    }
+
+    #[injectable(Trait, crate = facade::core)]
+    #[derive(Inject)]
+    #[injection(crate = facade::core)]
+    struct TestFacadeComponent {
+        #[default(4)]
+        pub value: u16
+    }
+
+    impl Trait for TestFacadeComponent {
+        fn get_value(&self) -> u16 {
+            self.value
+        }
+    }
+
+    #[injectable(Component, crate = facade::core)]
+    struct TestFacadeConsumer {
+        pub component: Arc<Mutex<dyn Trait>>
+    }
+
+    #[injector(crate = facade::core)]
+    impl TestFacadeConsumer {
+        #[inject]
+        fn new(component: Arc<Mutex<dyn Trait>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self { component })
+        }
+    }
+
+    #[injectable(Trait)]
+    struct TestProfileComponent {
+        pub value: u16
+    }
+
+    #[injector]
+    impl TestProfileComponent {
+        #[inject]
+        fn new() -> Result<Self> where Self: Sized + 'static {
+            Ok(Self { value: 1 })
+        }
+
+        #[inject(profile = "test")]
+        fn new_in_memory(#[named("first")] component: Arc<Mutex<dyn Trait>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self { value: component.lock().unwrap().get_value() + 10 })
+        }
+
+        #[inject]
+        fn with_value(value: Arc<Mutex<Constant<u16, {hash!("VALUE")}>>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self { value: value.lock().unwrap().value })
+        }
+    }
+
+    impl Trait for TestProfileComponent {
+        fn get_value(&self) -> u16 {
+            self.value
+        }
+    }
+
+    #[injectable(Component)]
+    struct TestDecorator {
+        pub inner: Arc<Mutex<dyn Trait>>
+    }
+
+    impl Trait for TestDecorator {
+        fn get_value(&self) -> u16 {
+            self.inner.lock().unwrap().get_value() + 100
+        }
+    }
+
+    #[injectable(Component)]
+    struct TestStorageConsumer {
+        pub rw: Arc<RwLock<dyn Trait>>,
+        pub shared: Arc<dyn Trait>
+    }
+
+    #[injector]
+    impl TestStorageConsumer {
+        #[inject]
+        fn new(rw: Arc<RwLock<dyn Trait>>, shared: Arc<dyn Trait>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self { rw, shared })
+        }
+    }
+
 
 
     #[test]
@@ -140,6 +515,528 @@ mod injection_tests {
         assert_eq!(t2, none_expected);
     }
 
+    #[test]
+    fn component_registry_get_named() {
+        let mut registry = Registry::new();
+        registry.register_named::<TestComponent>("first").unwrap();
+        registry.register_named::<TestComponent2>("second").unwrap();
+
+        let t1: Arc<Mutex<dyn Trait>> = registry.get_named::<dyn Trait>("first").unwrap();
+        let t2: Arc<Mutex<dyn Trait>> = registry.get_named::<dyn Trait>("second").unwrap();
+        let t3: RegistryError = registry.get_named::<dyn Trait>("third").unwrap_err().downcast().unwrap();
+        let none_expected = RegistryError::NoNamedComponentError { name: std::any::type_name::<dyn Trait>(), qualifier: "third".into() };
+
+        assert_eq!((*t1.lock().unwrap()).get_value(), 15);
+        assert_eq!((*t2.lock().unwrap()).get_value(), 10);
+        assert_eq!(t3, none_expected);
+
+        let consumer = registry.register::<TestNamedConsumer>().unwrap();
+        assert_eq!(consumer.lock().unwrap().first.lock().unwrap().get_value(), 15);
+        assert_eq!(consumer.lock().unwrap().second.lock().unwrap().get_value(), 10);
+    }
+
+    #[test]
+    fn component_registry_get_all() {
+        let mut registry = Registry::new();
+        assert!(registry.get_all::<dyn Trait>().unwrap().is_empty());
+
+        registry.register::<TestComponent>().unwrap();
+        registry.register::<TestComponent2>().unwrap();
+
+        let values: Vec<u16> = registry.get_all::<dyn Trait>().unwrap().iter().map(|t| t.lock().unwrap().get_value()).collect();
+        assert_eq!(values, vec![15, 10]);
+
+        let consumer = registry.register::<TestAllConsumer>().unwrap();
+        let values: Vec<u16> = consumer.lock().unwrap().all.iter().map(|t| t.lock().unwrap().get_value()).collect();
+        assert_eq!(values, vec![15, 10]);
+    }
+
+    #[test]
+    fn component_registry_lifetimes() {
+        let mut registry = Registry::new();
+        registry.register::<TestComponent>().unwrap();
+        let s1: Arc<Mutex<TestComponent>> = registry.get::<TestComponent>().unwrap();
+        let s2: Arc<Mutex<dyn Trait>> = registry.get::<dyn Trait>().unwrap();
+        s1.lock().unwrap().value = 20;
+        assert_eq!((*s2.lock().unwrap()).get_value(), 20);
+
+        let mut registry = Registry::new();
+        registry.register_transient::<TestComponent>().unwrap();
+        let t1: Arc<Mutex<TestComponent>> = registry.get::<TestComponent>().unwrap();
+        let t2: Arc<Mutex<TestComponent>> = registry.get::<TestComponent>().unwrap();
+        t1.lock().unwrap().value = 20;
+        assert!(!Arc::ptr_eq(&t1, &t2));
+        assert_eq!((*t2.lock().unwrap()).get_value(), 15);
+
+        let mut registry = Registry::new();
+        registry.register_scoped::<TestComponent>().unwrap();
+        let c1: Arc<Mutex<TestComponent>> = registry.get::<TestComponent>().unwrap();
+        let c2: Arc<Mutex<dyn Trait>> = registry.get::<dyn Trait>().unwrap();
+        c1.lock().unwrap().value = 20;
+        assert_eq!((*c2.lock().unwrap()).get_value(), 20);
+    }
+
+    #[test]
+    fn component_registry_scope() {
+        let mut registry = Registry::new();
+        registry.register::<TestComponent>().unwrap();
+        registry.register_scoped::<TestComponent2>().unwrap();
+        let root: Arc<Mutex<TestComponent2>> = registry.get::<TestComponent2>().unwrap();
+
+        {
+            let mut scope = registry.create_scope();
+            let s1: Arc<Mutex<TestComponent>> = scope.get::<TestComponent>().unwrap();
+            let s2: Arc<Mutex<TestComponent2>> = scope.get::<TestComponent2>().unwrap();
+            assert!(Arc::ptr_eq(&s1, &registry.get::<TestComponent>().unwrap()));
+            assert!(!Arc::ptr_eq(&s2, &root));
+            assert!(Arc::ptr_eq(&s2, &scope.get::<TestComponent2>().unwrap()));
+
+            scope.register_instance(TestComponent { value: 30 }).unwrap();
+            assert_eq!((*scope.get::<TestComponent>().unwrap().lock().unwrap()).get_value(), 30);
+            assert_eq!((*registry.get::<TestComponent>().unwrap().lock().unwrap()).get_value(), 15);
+        }
+
+        assert_eq!(registry.get_all::<TestComponent>().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn component_registry_scope_singleton() {
+        let mut registry = Registry::new();
+        registry.register_named_instance("first", TestComponent2 { value: 1 }).unwrap();
+        registry.register_named_instance("second", TestComponent2 { value: 2 }).unwrap();
+        registry.declare::<TestNamedConsumer>().unwrap();
+
+        {
+            // The singleton of the parent is built with the registrations of the parent
+            let mut scope = registry.create_scope();
+            scope.register_named_instance("first", TestComponent2 { value: 99 }).unwrap();
+            let consumer = scope.get::<TestNamedConsumer>().unwrap();
+            assert_eq!(consumer.lock().unwrap().first.lock().unwrap().get_value(), 1);
+        }
+
+        let consumer = registry.get::<TestNamedConsumer>().unwrap();
+        assert_eq!(consumer.lock().unwrap().first.lock().unwrap().get_value(), 1);
+    }
+
+    #[test]
+    fn component_registry_build() {
+        let mut registry = Registry::new();
+        registry.declare::<TestAllConsumer>().unwrap();
+        registry.declare::<TestNamedConsumer>().unwrap();
+        registry.declare::<TestComponent>().unwrap();
+        let t1: RegistryError = registry.build().unwrap_err().downcast().unwrap();
+        let missing_expected = RegistryError::MissingDependencyError {
+            component: std::any::type_name::<TestNamedConsumer>(),
+            dependency: format!("{} named first", std::any::type_name::<Arc<Mutex<dyn Trait>>>())
+        };
+        assert_eq!(t1, missing_expected);
+
+        registry.register_named_instance("first", TestComponent2 { value: 1 }).unwrap();
+        registry.register_named_instance("second", TestComponent2 { value: 2 }).unwrap();
+        registry.build().unwrap();
+
+        let values: Vec<u16> = registry.get::<TestAllConsumer>().unwrap().lock().unwrap().all.iter().map(|t| t.lock().unwrap().get_value()).collect();
+        assert_eq!(values, vec![15, 1, 2]);
+        assert_eq!(registry.get::<TestNamedConsumer>().unwrap().lock().unwrap().second.lock().unwrap().get_value(), 2);
+    }
+
+    #[test]
+    fn component_registry_build_cycle() {
+        let mut registry = Registry::new();
+        registry.declare::<TestCycleA>().unwrap();
+        registry.declare::<TestCycleB>().unwrap();
+        let t1: RegistryError = registry.build().unwrap_err().downcast().unwrap();
+        let cycle_expected = RegistryError::CircularDependencyError {
+            path: format!("{} -> {} -> {}", std::any::type_name::<TestCycleA>(), std::any::type_name::<TestCycleB>(), std::any::type_name::<TestCycleA>())
+        };
+        assert_eq!(t1, cycle_expected);
+    }
+
+    #[test]
+    fn component_registry_transient_cycle() {
+        let mut registry = Registry::new();
+        registry.register_transient::<TestCycleA>().unwrap();
+        registry.register_transient::<TestCycleB>().unwrap();
+        let error = registry.get::<TestCycleA>().err().unwrap();
+        let cycle_expected = RegistryError::CircularDependencyError {
+            path: format!("{} -> {} -> {}", std::any::type_name::<TestCycleA>(), std::any::type_name::<TestCycleB>(), std::any::type_name::<TestCycleA>())
+        };
+        assert_eq!(error.root_cause().downcast_ref::<RegistryError>(), Some(&cycle_expected));
+    }
+
+    #[test]
+    fn component_registry_register_async() {
+        let mut registry = Registry::new();
+        registry.register::<TestComponent>().unwrap();
+
+        let error = registry.register::<TestAsyncComponent>().unwrap_err();
+        let t1 = error.root_cause().downcast_ref::<RegistryError>().cloned().unwrap();
+        let async_expected = RegistryError::AsyncConstructorError { name: std::any::type_name::<TestAsyncComponent>() };
+        assert_eq!(t1, async_expected);
+
+        // A registration dropped while its constructor is pending does not leave the component under construction
+        assert!(registry.register_async::<TestAsyncComponent>().now_or_never().is_none());
+        let t2 = async_std::task::block_on(registry.register_async::<TestAsyncComponent>()).unwrap();
+        let t3 = async_std::task::block_on(registry.register_async::<TestComponent2>()).unwrap();
+        assert_eq!(t2.lock().unwrap().value, 16);
+        assert_eq!(t3.lock().unwrap().value, 10);
+    }
+
+    #[test]
+    fn component_registry_lazy_provider() {
+        let mut registry = Registry::new();
+        registry.declare::<TestLazyB>().unwrap();
+        registry.declare::<TestLazyA>().unwrap();
+        registry.register_transient::<TestComponent>().unwrap();
+        registry.build().unwrap();
+
+        let a = registry.get::<TestLazyA>().unwrap();
+        let b = a.lock().unwrap().b.get().unwrap();
+        assert!(Arc::ptr_eq(&b, &registry.get::<TestLazyB>().unwrap()));
+        assert!(Arc::ptr_eq(&b.lock().unwrap().a, &a));
+
+        let t1 = a.lock().unwrap().transient.get().unwrap();
+        let t2 = a.lock().unwrap().transient.get().unwrap();
+        assert!(!Arc::ptr_eq(&t1, &t2));
+    }
+
+    #[test]
+    fn component_registry_storage() {
+        let mut registry = Registry::new();
+        let rw = registry.register_instance(TestRwComponent { value: 1 }).unwrap();
+        let shared = registry.register_instance(TestSharedComponent { value: AtomicU16::new(2) }).unwrap();
+        let consumer = registry.register::<TestStorageConsumer>().unwrap();
+
+        rw.write().unwrap().value = 3;
+        shared.value.store(4, Ordering::Relaxed);
+        assert_eq!(consumer.lock().unwrap().rw.read().unwrap().get_value(), 3);
+        assert_eq!(consumer.lock().unwrap().shared.get_value(), 4);
+
+        assert!(Arc::ptr_eq(&registry.get_rw::<TestRwComponent>().unwrap(), &rw));
+        assert!(Arc::ptr_eq(&registry.get_shared::<TestSharedComponent>().unwrap(), &shared));
+        assert_eq!(registry.get_handle::<Arc<dyn Trait>>().unwrap().get_value(), 4);
+        assert!(registry.get::<dyn Trait>().is_err());
+    }
+
+    #[test]
+    fn component_registry_unregister_replace() {
+        let mut registry = Registry::new();
+        registry.register::<TestComponent>().unwrap();
+        let t1 = registry.register_instance(TestComponent2 { value: 1 }).unwrap();
+
+        let t2 = registry.get::<TestComponent>().unwrap() as Arc<Mutex<dyn Trait>>;
+        registry.unregister(&t2).unwrap();
+        assert!(registry.get::<TestComponent>().is_err());
+        assert_eq!(registry.get::<dyn Trait>().unwrap().lock().unwrap().get_value(), 1);
+        assert!(registry.unregister(&t2).is_err());
+
+        let error: RegistryError = registry.replace::<dyn UnknownTrait, _>(TestComponent2 { value: 3 }).unwrap_err().downcast().unwrap();
+        assert_eq!(error, RegistryError::ComponentTypeError { name: std::any::type_name::<TestComponent2>() });
+        assert_eq!(registry.get_all::<TestComponent2>().unwrap().len(), 1);
+
+        let t3 = registry.replace::<dyn Trait, _>(TestComponent2 { value: 2 }).unwrap();
+        assert_eq!(registry.get::<dyn Trait>().unwrap().lock().unwrap().get_value(), 2);
+        assert_eq!(registry.get_all::<TestComponent2>().unwrap().len(), 1);
+        assert!(Arc::ptr_eq(&registry.get::<TestComponent2>().unwrap(), &t3));
+        assert_eq!(t1.lock().unwrap().value, 1);
+    }
+
+    #[test]
+    fn component_registry_unregister_singleton() {
+        let mut registry = Registry::new();
+        registry.declare::<TestComponent>().unwrap();
+        registry.register_factory::<dyn Trait, _>(|_| Ok(Arc::new(Mutex::new(TestComponent2 { value: 1 })))).unwrap();
+        registry.build().unwrap();
+
+        let component = registry.get::<TestComponent>().unwrap() as Arc<Mutex<dyn Trait>>;
+        registry.unregister(&component).unwrap();
+        assert!(registry.get::<TestComponent>().is_err());
+
+        let factory = registry.get::<dyn Trait>().unwrap();
+        registry.unregister(&factory).unwrap();
+        assert!(registry.get_all::<dyn Trait>().unwrap().is_empty());
+
+        let error: RegistryError = registry.unregister(&factory).unwrap_err().downcast().unwrap();
+        assert_eq!(error, RegistryError::UnknownHandleError { name: std::any::type_name::<Arc<Mutex<dyn Trait>>>() });
+    }
+
+    #[test]
+    fn component_registry_override() {
+        let mut registry = Registry::new();
+        registry.register::<TestComponent>().unwrap();
+        registry.override_with::<dyn Trait, _>(TestComponent2 { value: 1 }).unwrap();
+        registry.register_named_instance("first", TestComponent2 { value: 2 }).unwrap();
+        registry.register_named_instance("second", TestComponent2 { value: 3 }).unwrap();
+
+        assert_eq!(registry.get::<dyn Trait>().unwrap().lock().unwrap().get_value(), 1);
+        assert_eq!(registry.get_all::<dyn Trait>().unwrap().len(), 1);
+        assert_eq!(registry.get_all::<TestComponent2>().unwrap().len(), 3);
+        assert!(registry.override_with::<dyn UnknownTrait, _>(TestComponent2 { value: 4 }).is_err());
+
+        let consumer = registry.register::<TestAllConsumer>().unwrap();
+        assert_eq!(consumer.lock().unwrap().all.len(), 1);
+
+        // The override does not hide the components resolved with a qualifier
+        assert_eq!(registry.get_named::<dyn Trait>("second").unwrap().lock().unwrap().get_value(), 3);
+        let consumer = registry.register::<TestNamedConsumer>().unwrap();
+        assert_eq!(consumer.lock().unwrap().first.lock().unwrap().get_value(), 2);
+        assert!(registry.describe().components.iter().any(|component| component.dependencies.iter().any(|dependency| dependency.name == Some("first") && dependency.providers.len() == 1)));
+    }
+
+    #[test]
+    fn microservice_overrides() {
+        let mut m = microservice::testing::Overrides::new()
+            .with::<dyn Trait, _>(TestComponent2 { value: 1 })
+            .build()
+            .unwrap();
+        m.register::<TestComponent>().unwrap();
+        assert_eq!(m.get::<dyn Trait>().unwrap().lock().unwrap().get_value(), 1);
+    }
+
+    #[test]
+    fn component_registry_primary_priority() {
+        let mut registry = Registry::new();
+        registry.register::<TestComponent>().unwrap();
+        registry.register::<TestComponent2>().unwrap();
+        assert!(registry.get::<dyn Trait>().is_err());
+
+        registry.register_instance(TestPriorityComponent {}).unwrap();
+        assert_eq!(registry.get::<dyn Trait>().unwrap().lock().unwrap().get_value(), 2);
+
+        registry.register_instance(TestPrimaryComponent {}).unwrap();
+        assert_eq!(registry.get::<dyn Trait>().unwrap().lock().unwrap().get_value(), 1);
+        assert_eq!(registry.get_all::<dyn Trait>().unwrap().len(), 4);
+
+        registry.register_instance(TestPrimaryComponent {}).unwrap();
+        let multiple_expected = RegistryError::MultipleComponentsError { name: std::any::type_name::<dyn Trait>() };
+        let t1: RegistryError = registry.get::<dyn Trait>().unwrap_err().downcast().unwrap();
+        assert_eq!(t1, multiple_expected);
+    }
+
+    #[test]
+    fn component_registry_condition() {
+        let mut registry = Registry::new();
+        registry.declare::<TestMemoryBackend>().unwrap();
+        registry.build().unwrap();
+        assert!(registry.get::<dyn Trait>().is_err());
+
+        registry.register_instance(TestConfig { backend: "memory".into() }).unwrap();
+        registry.declare::<TestMemoryBackend>().unwrap();
+        registry.declare::<TestRemoteBackend>().unwrap();
+        registry.build().unwrap();
+        assert_eq!(registry.get::<dyn Trait>().unwrap().lock().unwrap().get_value(), 1);
+
+        let condition_expected = RegistryError::ConditionError { name: std::any::type_name::<TestRemoteBackend>(), condition: "backend == 'remote'" };
+        let t1: RegistryError = registry.register::<TestRemoteBackend>().unwrap_err().downcast().unwrap();
+        assert_eq!(t1, condition_expected);
+        let t2: RegistryError = registry.register_named::<TestRemoteBackend>("remote").unwrap_err().downcast().unwrap();
+        assert_eq!(t2, condition_expected);
+        assert!(registry.register_transient::<TestRemoteBackend>().is_err());
+        assert_eq!(registry.get_all::<dyn Trait>().unwrap().len(), 1);
+
+        assert!(registry.register_if::<TestRemoteBackend, _>(|_| true).unwrap().is_none());
+
+        assert!(registry.register_if::<TestComponent, _>(|config| config.get("debug").is_ok()).unwrap().is_none());
+        assert!(registry.register_if::<TestComponent, _>(|config| config.get("backend").is_ok()).unwrap().is_some());
+    }
+
+    #[test]
+    fn component_registry_config() {
+        let mut registry = Registry::new();
+        registry.register_instance(TestConfig { backend: "memory".into() }).unwrap();
+        let consumer = registry.register::<TestConfigConsumer>().unwrap();
+        assert_eq!(consumer.lock().unwrap().backend, "memory");
+        assert_eq!(consumer.lock().unwrap().port, 8080);
+        assert!(TestConfigConsumer::new_dependencies().is_empty());
+
+        let t1: microservice::config::ConfigError = registry.register::<TestMissingConfigConsumer>().unwrap_err().downcast().unwrap();
+        assert_eq!(t1.to_string(), format!("Config - Unable to inject the field timeout in the component {}", std::any::type_name::<TestMissingConfigConsumer>()));
+    }
+
+    #[test]
+    fn component_registry_optional() {
+        let mut registry = Registry::new();
+        registry.declare::<TestOptionalConsumer>().unwrap();
+        registry.declare::<TestComponent>().unwrap();
+        registry.build().unwrap();
+        let consumer = registry.get::<TestOptionalConsumer>().unwrap();
+        assert_eq!(consumer.lock().unwrap().component.as_ref().unwrap().lock().unwrap().get_value(), 15);
+        assert!(consumer.lock().unwrap().unknown.is_none());
+
+        registry.register::<TestComponent2>().unwrap();
+        let multiple_expected = RegistryError::MultipleComponentsError { name: std::any::type_name::<Arc<Mutex<dyn Trait>>>() };
+        let error = registry.register::<TestOptionalConsumer>().err().unwrap();
+        let t1 = error.root_cause().downcast_ref::<RegistryError>().cloned().unwrap();
+        assert_eq!(t1, multiple_expected);
+    }
+
+    #[test]
+    fn component_registry_generic_trait_enum() {
+        let mut registry = Registry::new();
+        registry.register_instance(TestMemoryRepository {}).unwrap();
+        registry.register_instance(TestEnumComponent::Value(3)).unwrap();
+        registry.register_named_instance("fixed", TestEnumComponent::Fixed).unwrap();
+
+        assert_eq!(registry.get::<dyn TestRepository<u16>>().unwrap().lock().unwrap().find(2), Some(2));
+        assert_eq!(registry.get::<dyn TestRepository<String>>().unwrap().lock().unwrap().find(2), Some("2".into()));
+        assert!(registry.get::<dyn TestRepository<u32>>().is_err());
+        assert_eq!(registry.get_named::<dyn Trait>("fixed").unwrap().lock().unwrap().get_value(), 1);
+        assert_eq!(registry.get_all::<TestEnumComponent>().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn component_registry_crate_path() {
+        let mut registry = Registry::new();
+        registry.register::<TestFacadeComponent>().unwrap();
+        let consumer = registry.register::<TestFacadeConsumer>().unwrap();
+        assert_eq!(consumer.lock().unwrap().component.lock().unwrap().get_value(), 4);
+    }
+
+    #[test]
+    fn component_registry_constructors() {
+        let mut registry = Registry::new();
+        registry.register_named_instance("first", TestComponent2 { value: 2 }).unwrap();
+        registry.register_instance(Constant::<u16, {hash!("VALUE")}>::new(7)).unwrap();
+
+        let t1 = registry.register_with::<TestProfileComponent, _>(TestProfileComponent::with_value_from_reg, TestProfileComponent::with_value_dependencies()).unwrap();
+        assert_eq!(t1.lock().unwrap().value, 7);
+        let t2 = registry.register_profile::<TestProfileComponent>("test").unwrap();
+        assert_eq!(t2.lock().unwrap().value, 12);
+        let t3 = registry.register_profile::<TestProfileComponent>("production").unwrap();
+        assert_eq!(t3.lock().unwrap().value, 1);
+
+        let description = registry.describe();
+        let components: Vec<_> = description.components.iter().filter(|component| component.component == std::any::type_name::<TestProfileComponent>()).collect();
+        let constant = description.components.iter().find(|component| component.component == std::any::type_name::<Constant<u16, {hash!("VALUE")}>>()).unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0].dependencies.len(), 1);
+        assert_eq!(components[0].dependencies[0].providers, vec![constant.id]);
+        assert_eq!(components[1].dependencies.len(), 1);
+        assert_eq!(components[1].dependencies[0].name, Some("first"));
+        assert!(TestProfileComponent::profile_constructor("production").is_none());
+    }
+
+    #[test]
+    fn component_registry_derive_inject() {
+        let mut registry = Registry::new();
+        registry.declare::<TestDerivedConsumer>().unwrap();
+        registry.register_instance(TestConfig { backend: "memory".into() }).unwrap();
+        registry.register_named_instance("first", TestComponent2 { value: 1 }).unwrap();
+        registry.register_named_instance("second", TestComponent2 { value: 2 }).unwrap();
+        registry.build().unwrap();
+
+        let consumer = registry.get::<TestDerivedConsumer>().unwrap();
+        let consumer = consumer.lock().unwrap();
+        assert_eq!(consumer.first.lock().unwrap().get_value(), 1);
+        assert_eq!(consumer.all.len(), 2);
+        assert!(consumer.unknown.is_none());
+        assert_eq!(consumer.port, 8080);
+        assert_eq!(consumer.calls, 0);
+        assert_eq!(consumer.label, "derived");
+        assert_eq!(TestDerivedConsumer::new_dependencies().len(), 3);
+    }
+
+    #[test]
+    fn component_registry_constructor_error() {
+        let mut registry = Registry::new();
+        registry.declare::<TestMissingConfigConsumer>().unwrap();
+        registry.register_factory::<dyn Trait, _>(|registry| {
+            registry.get::<TestMissingConfigConsumer>()?;
+            Ok(Arc::new(Mutex::new(TestComponent2 { value: 1 })))
+        }).unwrap();
+
+        let error = registry.get::<dyn Trait>().unwrap_err();
+        let chain = format!("{} -> {}", std::any::type_name::<dyn Trait>(), std::any::type_name::<TestMissingConfigConsumer>());
+        let constructor_expected = RegistryError::ConstructorError { component: std::any::type_name::<TestMissingConfigConsumer>(), chain };
+        assert_eq!(error.downcast_ref::<RegistryError>(), Some(&constructor_expected));
+        let config_expected = RegistryError::NoComponentError { name: std::any::type_name::<dyn Config>() };
+        assert_eq!(error.root_cause().downcast_ref::<RegistryError>(), Some(&config_expected));
+
+        let error = registry.register::<TestNamedConsumer>().err().unwrap();
+        let constructor_expected = RegistryError::ConstructorError { component: std::any::type_name::<TestNamedConsumer>(), chain: std::any::type_name::<TestNamedConsumer>().into() };
+        assert_eq!(error.downcast_ref::<RegistryError>(), Some(&constructor_expected));
+    }
+
+    #[test]
+    fn component_registry_factory() {
+        let mut registry = Registry::new();
+        registry.register_factory::<dyn Trait, _>(|registry| {
+            let value = registry.get::<Constant<u16, {hash!("VALUE")}>>()?.lock().unwrap().value;
+            Ok(Arc::new(Mutex::new(TestComponent2 { value: value + 1 })))
+        }).unwrap();
+        registry.register_instance(Constant::<u16, {hash!("VALUE")}>::new(15)).unwrap();
+        registry.build().unwrap();
+
+        let t1 = registry.get::<dyn Trait>().unwrap();
+        assert_eq!(t1.lock().unwrap().get_value(), 16);
+        assert!(Arc::ptr_eq(&t1, &registry.get::<dyn Trait>().unwrap()));
+        assert!(registry.get::<TestComponent2>().is_err());
+    }
+
+    #[test]
+    fn component_registry_describe() {
+        let mut registry = Registry::new();
+        registry.register_named_instance("first", TestComponent2 { value: 1 }).unwrap();
+        registry.register_named_instance("second", TestComponent2 { value: 2 }).unwrap();
+        registry.register::<TestNamedConsumer>().unwrap();
+        let mut scope = registry.create_scope();
+        scope.register_scoped::<TestComponent>().unwrap();
+
+        let description = scope.describe();
+        assert_eq!(description.components.len(), 4);
+        let component = &description.components[0];
+        assert_eq!(component.component, std::any::type_name::<TestComponent>());
+        assert_eq!((component.scope, component.lifetime), (0, Lifetime::Scoped));
+        assert!(component.provides.contains(&std::any::type_name::<Arc<Mutex<dyn Trait>>>()));
+        assert_eq!(component.provides.len(), 3);
+
+        let first = &description.components[1];
+        let consumer = &description.components[3];
+        assert_eq!((first.name.as_deref(), first.scope), (Some("first"), 1));
+        assert_eq!(consumer.dependencies[0].name, Some("first"));
+        assert_eq!(consumer.dependencies[0].providers, vec![first.id]);
+
+        assert_eq!((first.id, consumer.id), (1, 3));
+        assert!(description.to_dot().contains("c3 -> c1;"));
+
+        // The numbering does not depend on the other registries
+        Registry::new().register_instance(TestComponent { value: 0 }).unwrap();
+        assert_eq!(scope.describe(), description);
+        assert!(description.to_dot().contains("Arc<Mutex<dyn Trait>>"));
+        assert!(description.to_json().unwrap().contains(&format!("\"component\": \"{}\"", std::any::type_name::<TestNamedConsumer>())));
+    }
+
+    #[test]
+    fn component_registry_decorate() {
+        let mut registry = Registry::new();
+        registry.register::<TestComponent>().unwrap();
+        registry.decorate::<dyn Trait, _>(|inner| TestDecorator { inner }.into_handle());
+        registry.register_transient::<TestComponent2>().unwrap();
+
+        let all = registry.get_all::<dyn Trait>().unwrap();
+        assert_eq!(all.iter().map(|component| component.lock().unwrap().get_value()).collect::<Vec<u16>>(), vec![115, 110]);
+        assert!(Arc::ptr_eq(&all[0], &registry.get_all::<dyn Trait>().unwrap()[0]));
+        assert_eq!(registry.get::<TestComponent>().unwrap().lock().unwrap().get_value(), 15);
+
+        registry.decorate::<dyn Trait, _>(|inner| TestDecorator { inner }.into_handle());
+        assert_eq!(registry.get_all::<dyn Trait>().unwrap()[1].lock().unwrap().get_value(), 210);
+    }
+
+    #[test]
+    fn component_registry_decorate_scope() {
+        let mut registry = Registry::new();
+        registry.decorate::<dyn Trait, _>(|inner| TestDecorator { inner }.into_handle());
+
+        let mut scope = registry.create_scope();
+        scope.register_instance(TestComponent2 { value: 1 }).unwrap();
+        assert_eq!(scope.get::<dyn Trait>().unwrap().lock().unwrap().get_value(), 101);
+
+        scope.decorate::<dyn Trait, _>(|inner| TestDecorator { inner }.into_handle());
+        scope.register_instance(TestComponent2 { value: 2 }).unwrap();
+        assert_eq!(scope.get_all::<dyn Trait>().unwrap().iter().map(|component| component.lock().unwrap().get_value()).collect::<Vec<u16>>(), vec![201, 202]);
+        assert!(registry.get::<dyn Trait>().is_err());
+    }
+
     #[test]
     fn component_registry_mutability() {
         let mut registry = Registry::new();
@@ -158,7 +1055,7 @@ mod injection_tests {
     #[test]
     fn microservice_register_retrieve_constant() {
         let mut m: Microservice = Microservice::new();
-        m.register_instance(Constant::<String, {hash!("CONFIG_FILE")}>::new("./config.yaml".into()));
+        m.register_instance(Constant::<String, {hash!("CONFIG_FILE")}>::new("./config.yaml".into())).unwrap();
         let c = m.get::<Constant<String, {hash!("CONFIG_FILE")}>>().unwrap();
         assert_eq!(c.lock().unwrap().value, "./config.yaml");
     }
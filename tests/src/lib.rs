@@ -1,2 +1,4 @@
 mod url;
 mod injection;
+mod lifecycle;
+mod macros;
@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod lifecycle_tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use async_trait::async_trait;
+    use microservice::error::*;
+    use microservice::injection::*;
+    use microservice::lifecycle::Lifecycle;
+    use microservice::shutdown::ShutdownError;
+    use microservice::*;
+
+    #[injectable(Component)]
+    struct TestEvents {
+        pub events: Vec<String>
+    }
+
+    #[injectable(Lifecycle)]
+    struct TestDatabase {
+        events: Arc<Mutex<TestEvents>>
+    }
+
+    #[injector]
+    impl TestDatabase {
+        #[inject]
+        fn new(events: Arc<Mutex<TestEvents>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {
+                events
+            })
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl Lifecycle for TestDatabase {
+        async fn start(&mut self) -> Result<()> {
+            self.events.lock().unwrap().events.push("start database".into());
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> Result<()> {
+            self.events.lock().unwrap().events.push("stop database".into());
+            Ok(())
+        }
+    }
+
+    #[injectable(Lifecycle)]
+    struct TestServer {
+        events: Arc<Mutex<TestEvents>>
+    }
+
+    #[injector]
+    impl TestServer {
+        #[inject]
+        fn new(events: Arc<Mutex<TestEvents>>, _database: Arc<Mutex<TestDatabase>>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {
+                events
+            })
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl Lifecycle for TestServer {
+        async fn start(&mut self) -> Result<()> {
+            self.events.lock().unwrap().events.push("start server".into());
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> Result<()> {
+            self.events.lock().unwrap().events.push("stop server".into());
+            Ok(())
+        }
+    }
+
+    #[injectable(Lifecycle)]
+    struct TestBlocked {}
+
+    #[async_trait(?Send)]
+    impl Lifecycle for TestBlocked {
+        async fn stop(&mut self) -> Result<()> {
+            futures::future::pending::<()>().await;
+            Ok(())
+        }
+    }
+
+    #[injectable(Lifecycle)]
+    struct TestCache {
+        database: Lazy<TestDatabase>
+    }
+
+    #[injector]
+    impl TestCache {
+        #[inject]
+        fn new(database: Lazy<TestDatabase>) -> Result<Self> where Self: Sized + 'static {
+            Ok(Self {
+                database
+            })
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl Lifecycle for TestCache {
+        async fn start(&mut self) -> Result<()> {
+            self.database.get()?.lock().unwrap().events.lock().unwrap().events.push("start cache".into());
+            Ok(())
+        }
+    }
+
+    #[injectable(Lifecycle)]
+    trait TestServerTrait: Lifecycle {}
+
+    #[injectable(TestServerTrait, storage = rwlock)]
+    struct TestRwServer {}
+
+    impl TestServerTrait for TestRwServer {}
+    impl Lifecycle for TestRwServer {}
+
+    #[test]
+    fn microservice_start_stop_order() {
+        let mut m: Microservice = Microservice::new();
+        let events = m.register_instance(TestEvents { events: Vec::new() }).unwrap();
+        m.declare::<TestServer>().unwrap();
+        m.declare::<TestDatabase>().unwrap();
+        m.build().unwrap();
+
+        async_std::task::block_on(async {
+            m.start().await.unwrap();
+            m.stop().await.unwrap();
+        });
+
+        assert_eq!(events.lock().unwrap().events, vec!["start database", "start server", "stop server", "stop database"]);
+    }
+
+    #[test]
+    fn microservice_start_lazy() {
+        let mut m: Microservice = Microservice::new();
+        let events = m.register_instance(TestEvents { events: Vec::new() }).unwrap();
+        m.register::<TestDatabase>().unwrap();
+        m.register::<TestCache>().unwrap();
+
+        async_std::task::block_on(m.start()).unwrap();
+
+        assert_eq!(events.lock().unwrap().events, vec!["start database", "start cache"]);
+    }
+
+    #[test]
+    fn microservice_start_storage() {
+        let mut m: Microservice = Microservice::new();
+        m.register_instance(TestRwServer {}).unwrap();
+
+        let error = async_std::task::block_on(m.start()).unwrap_err();
+        assert_eq!(error.downcast_ref::<RegistryError>(), Some(&RegistryError::StorageError { name: std::any::type_name::<dyn Lifecycle>() }));
+    }
+
+    #[test]
+    fn microservice_run_shutdown() {
+        let mut m: Microservice = Microservice::new();
+        let events = m.register_instance(TestEvents { events: Vec::new() }).unwrap();
+        m.register::<TestDatabase>().unwrap();
+
+        let handle = m.shutdown_handle();
+        async_std::task::block_on(async {
+            async_std::task::spawn(async move { handle.shutdown() });
+            m.run().await.unwrap();
+        });
+
+        assert_eq!(events.lock().unwrap().events, vec!["start database", "stop database"]);
+    }
+
+    #[test]
+    fn microservice_run_shutdown_timeout() {
+        let mut m: Microservice = Microservice::new();
+        m.register_instance(TestBlocked {}).unwrap();
+        m.set_shutdown_timeout(Duration::from_millis(10));
+
+        m.shutdown_handle().shutdown();
+        let result = async_std::task::block_on(m.run());
+        match result.unwrap_err().downcast::<ShutdownError>() {
+            Ok(ShutdownError::Timeout { timeout }) => assert_eq!(timeout, Duration::from_millis(10)),
+            _ => panic!("A shutdown timeout was expected")
+        }
+    }
+}
@@ -0,0 +1,9 @@
+#[cfg(test)]
+mod macros_tests {
+    // The diagnostics reported by the macros are compared with the .stderr files next to the sources
+    #[test]
+    fn compile_errors() {
+        let cases = trybuild::TestCases::new();
+        cases.compile_fail("ui/*.rs");
+    }
+}
@@ -0,0 +1,17 @@
+use microservice::error::Result;
+use microservice::injection::*;
+use microservice::*;
+
+#[injectable(Component)]
+struct TestComponent {
+    value: u16
+}
+
+impl TestComponent {
+    #[inject]
+    fn new() -> Result<Self> where Self: Sized + 'static {
+        Ok(Self { value: 0 })
+    }
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use microservice::*;
+
+#[injectable(Component)]
+fn component() {}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use microservice::injection::*;
+use microservice::*;
+
+#[injectable(Component)]
+struct TestComponent {
+    value: u16
+}
+
+#[injector]
+impl TestComponent {
+    #[inject]
+    fn new(&self) -> microservice::error::Result<Self> where Self: Sized + 'static {
+        Ok(Self { value: self.value })
+    }
+}
+
+fn main() {}
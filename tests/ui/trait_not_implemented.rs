@@ -0,0 +1,14 @@
+use microservice::injection::*;
+use microservice::*;
+
+#[injectable(Component)]
+trait Trait: Component {
+    fn get_value(&self) -> u16;
+}
+
+#[injectable(Trait)]
+struct TestComponent {
+    value: u16
+}
+
+fn main() {}